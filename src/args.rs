@@ -19,6 +19,24 @@ pub struct PanReleaseArgs {
 pub enum Commands {
     /// Release a new version
     Release(RelArgs),
+    /// Show the detected project layout
+    Show,
+    /// Inspect the panrelease configuration
+    Config(ConfigArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    #[clap(subcommand)]
+    pub subcommand: ConfigCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Print the JSON Schema of .panproject.toml
+    Schema,
+    /// Print the effective configuration and where each value comes from
+    Show,
 }
 
 #[derive(Args, Debug)]
@@ -26,6 +44,9 @@ pub struct RelArgs {
     /// Either bump by LEVEL or set the VERSION for all selected packages
     #[arg(value_name = "LEVEL|VERSION", help_heading = "Version")]
     pub level_or_version: TargetVersion,
+    /// Release even when tracked files have uncommitted changes
+    #[arg(long)]
+    pub allow_dirty: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+use crate::project::diagnostics;
+use crate::system::FileSystem;
+
+const EXTENDS_KEY: &str = "extends";
+
+/// Origin of every configuration value, by dotted key path
+pub type Origins = BTreeMap<String, String>;
+
+/// Configuration value in effect with the place it was defined in
+#[derive(Debug, Clone)]
+pub struct ValueOrigin {
+    pub key: String,
+    pub value: Value,
+    pub origin: String,
+}
+
+/// Merge the files listed in the `extends` key of `layer` below it, recursively.
+/// Later bases override earlier ones and `layer` overrides all of them; tables are merged key by key
+/// while any other value, arrays included, is replaced as a whole.
+pub fn resolve<F: FileSystem>(layer: Value, file: &Path, label: &str, root: &Path, origins: &mut Origins) -> anyhow::Result<Value> {
+    resolve_layer::<F>(layer, file, label, root, origins, &mut Vec::new())
+}
+
+fn resolve_layer<F: FileSystem>(
+    mut layer: Value,
+    file: &Path,
+    label: &str,
+    root: &Path,
+    origins: &mut Origins,
+    chain: &mut Vec<PathBuf>,
+) -> anyhow::Result<Value> {
+    let file = normalize(file);
+    if chain.contains(&file) {
+        let cycle = chain.iter()
+            .chain(std::iter::once(&file))
+            .map(|f| f.display().to_string())
+            .collect::<Vec<_>>();
+        anyhow::bail!("Cyclic extends: {}", cycle.join(" -> "));
+    }
+    chain.push(file.clone());
+
+    let bases = take_extends(&mut layer)
+        .with_context(|| format!("Invalid extends in {label}"))?;
+    let mut merged = Value::Object(Map::new());
+    for base in bases {
+        let base_file = file.parent().unwrap_or(Path::new("")).join(base);
+        let base_layer = read_layer::<F>(&base_file)?;
+        let base_label = relative_to(&normalize(&base_file), root).display().to_string();
+        let resolved = resolve_layer::<F>(base_layer, &base_file, &base_label, root, origins, chain)?;
+        merge(&mut merged, resolved, None, "", origins);
+    }
+    merge(&mut merged, layer, Some(label), "", origins);
+
+    chain.pop();
+    Ok(merged)
+}
+
+/// Read an inherited file, its keys checked against the configuration schema to report errors in the file itself.
+/// Required values may be defined by the other layers, only the merged configuration must have them.
+fn read_layer<F: FileSystem>(file: &Path) -> anyhow::Result<Value> {
+    let content = F::read_string(file)
+        .with_context(|| format!("Failed to read inherited configuration {}", file.display()))?;
+    let layer = if file.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&content).map_err(|e| diagnostics::json_error(file, e))?
+    } else {
+        toml::from_str(&content).map_err(|e| diagnostics::toml_error(file, &content, e))?
+    };
+    diagnostics::check_keys(file, &content, &layer)?;
+    Ok(layer)
+}
+
+/// Whether the configuration inherits from other files, and so may be incomplete on its own
+pub fn declares_extends(layer: &Value) -> bool {
+    layer.get(EXTENDS_KEY).is_some()
+}
+
+fn take_extends(layer: &mut Value) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(extends) = layer.as_object_mut().and_then(|table| table.remove(EXTENDS_KEY)) else {
+        return Ok(Vec::new());
+    };
+    Ok(serde_json::from_value(extends)?)
+}
+
+/// Merge `overlay` into `base`, recording `label` as the origin of every overlaid value when given
+fn merge(base: &mut Value, overlay: Value, label: Option<&str>, prefix: &str, origins: &mut Origins) {
+    match (base, overlay) {
+        (Value::Object(base_table), Value::Object(overlay_table)) => {
+            for (key, value) in overlay_table {
+                let path = join_key(prefix, &key);
+                let entry = base_table.entry(key).or_insert(Value::Null);
+                if value.is_object() && !entry.is_object() {
+                    *entry = Value::Object(Map::new());
+                }
+                merge(entry, value, label, &path, origins);
+            }
+        }
+        (base, overlay) => {
+            *base = overlay;
+            if let Some(label) = label {
+                origins.retain(|key, _| !is_below(key, prefix));
+                origins.insert(prefix.to_string(), label.to_string());
+            }
+        }
+    }
+}
+
+/// List every value of the effective configuration with the place it was defined in.
+/// Values found in no layer come from the defaults.
+pub fn explain(effective: &Value, origins: &Origins) -> Vec<ValueOrigin> {
+    let mut values = Vec::new();
+    flatten(effective, "", &mut values);
+    values.into_iter()
+        .map(|(key, value)| ValueOrigin {
+            origin: origin_of(&key, origins).unwrap_or_else(|| String::from("default")),
+            key,
+            value,
+        })
+        .collect()
+}
+
+fn flatten(value: &Value, prefix: &str, values: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(table) if !table.is_empty() => {
+            for (key, value) in table {
+                flatten(value, &join_key(prefix, key), values);
+            }
+        }
+        _ => values.push((prefix.to_string(), value.clone())),
+    }
+}
+
+fn origin_of(key: &str, origins: &Origins) -> Option<String> {
+    if let Some(origin) = origins.get(key) {
+        return Some(origin.clone());
+    }
+    // Modules declaring a single `packageManager` are normalized to the `packageManagers` list
+    if let Some(module) = key.strip_suffix(".packageManagers") {
+        return origins.get(&format!("{module}.packageManager")).cloned();
+    }
+    origins.iter()
+        .filter(|(k, _)| is_below(key, k))
+        .max_by_key(|(k, _)| k.len())
+        .map(|(_, origin)| origin.clone())
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") }
+}
+
+fn is_below(key: &str, ancestor: &str) -> bool {
+    ancestor.is_empty() || key == ancestor || key.starts_with(&format!("{ancestor}."))
+}
+
+/// Resolve `.` and `..` components without touching the file system, to detect cycles on equal paths
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Path of `path` from `base`, both being normalized, to label files outside of the project root
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let common = path.components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+    base.components().skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn local_values_override_inherited_ones() {
+        let mut origins = Origins::new();
+        let mut merged = json!({});
+        merge(&mut merged, json!({ "vcs": { "software": "Git", "tag_template": "v{{version}}" }, "hooks": ["a", "b"] }), Some("base.toml"), "", &mut origins);
+        merge(&mut merged, json!({ "vcs": { "force_sign": true }, "hooks": ["c"] }), Some(".panproject.toml"), "", &mut origins);
+
+        assert_eq!(json!({ "vcs": { "software": "Git", "tag_template": "v{{version}}", "force_sign": true }, "hooks": ["c"] }), merged);
+        let explained = explain(&merged, &origins).into_iter()
+            .map(|v| (v.key, v.origin))
+            .collect::<Vec<_>>();
+        assert!(explained.contains(&(String::from("vcs.tag_template"), String::from("base.toml"))));
+        assert!(explained.contains(&(String::from("vcs.force_sign"), String::from(".panproject.toml"))));
+        assert!(explained.contains(&(String::from("hooks"), String::from(".panproject.toml"))));
+    }
+
+    #[test]
+    fn normalize_parent_components() {
+        assert_eq!(PathBuf::from("/repo/shared/base.toml"), normalize(Path::new("/repo/app/./../shared/base.toml")));
+        assert_eq!(PathBuf::from("../shared/base.toml"), normalize(Path::new("../shared/base.toml")));
+        assert_eq!(PathBuf::from("../shared/base.toml"), relative_to(Path::new("/repo/shared/base.toml"), Path::new("/repo/app")));
+    }
+}
@@ -1,20 +1,16 @@
 use std::path::PathBuf;
+use anyhow::Context;
 use crate::project::core::PanProject;
-use crate::system::FileSystem;
+use crate::system::{EnvVars, FileSystem};
 pub struct ConfigLoader;
 
 impl ConfigLoader {
-    pub fn parse_config<S: FileSystem + 'static>(repo_path: Option<PathBuf>) -> anyhow::Result<PanProject<S>> {
+    pub fn parse_config<S: FileSystem + EnvVars + 'static>(repo_path: Option<PathBuf>) -> anyhow::Result<PanProject<S>> {
         let Ok(cwd) = repo_path.map(Ok).unwrap_or_else(S::current_dir) else {
             anyhow::bail!("Error loading current directory");
         };
 
-        let project = match PanProject::load(cwd.as_path()) {
-            Ok(p) => p,
-            Err(e) => {
-                anyhow::bail!("Error loading project - {e}");
-            }
-        };
-        Ok(project)
+        PanProject::load(cwd.as_path())
+            .context("Error loading project")
     }
 }
\ No newline at end of file
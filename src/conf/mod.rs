@@ -1 +1,4 @@
-pub mod loader;
\ No newline at end of file
+pub mod extends;
+pub mod loader;
+pub mod overrides;
+pub mod schema;
\ No newline at end of file
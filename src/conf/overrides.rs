@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// Prefix of the environment variables overriding configuration values
+pub const ENV_PREFIX: &str = "PANRELEASE_";
+const PATH_SEPARATOR: &str = "__";
+
+/// Apply `PANRELEASE_`-stripped variables such as `VCS__FORCE_SIGN` to the configuration value.
+/// Variables without a nested path are not configuration overrides and are ignored.
+/// Returns the dotted path of every overridden value with the variable overriding it.
+pub fn apply_env_overrides(config: &mut Value, vars: &HashMap<String, String>) -> anyhow::Result<Vec<(String, String)>> {
+    let mut applied = Vec::new();
+    let mut keys = vars.keys().collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        let segments = key.split(PATH_SEPARATOR).collect::<Vec<_>>();
+        if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+            log::debug!("Ignoring {ENV_PREFIX}{key}, not a configuration override");
+            continue;
+        }
+        let path = set_path(config, &segments, &vars[key])
+            .map_err(|e| anyhow::anyhow!("Error applying {ENV_PREFIX}{key} - {e}"))?;
+        applied.push((path, format!("{ENV_PREFIX}{key}")));
+    }
+    Ok(applied)
+}
+
+fn set_path(config: &mut Value, segments: &[&str], raw: &str) -> anyhow::Result<String> {
+    let mut current = config;
+    let mut path = Vec::new();
+    for (idx, segment) in segments.iter().enumerate() {
+        let Value::Object(table) = current else {
+            anyhow::bail!("'{segment}' is not inside a table");
+        };
+        let key = matching_key(table, segment);
+        path.push(key.clone());
+        // missing tables on the way are created, e.g. a module only declared through the environment
+        let missing = if idx + 1 < segments.len() { Value::Object(Map::new()) } else { Value::Null };
+        current = table.entry(key).or_insert(missing);
+    }
+    *current = typed_value(current, raw);
+    Ok(path.join("."))
+}
+
+/// Existing key matching the segment ignoring case, to address camelCase keys and module names
+fn matching_key(table: &Map<String, Value>, segment: &str) -> String {
+    table.keys()
+        .find(|k| k.eq_ignore_ascii_case(segment))
+        .cloned()
+        .unwrap_or_else(|| segment.to_lowercase())
+}
+
+/// Parse the raw value following the type of the value being replaced
+fn typed_value(current: &Value, raw: &str) -> Value {
+    match current {
+        Value::String(_) => Value::String(raw.to_string()),
+        Value::Number(_) => raw.parse::<serde_json::Number>()
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        Value::Array(_) | Value::Object(_) => serde_json::from_str(raw)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        Value::Bool(_) | Value::Null => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn override_existing_and_missing_values() {
+        let mut config = json!({
+            "vcs": { "software": "Git", "tag_template": "{{version}}" },
+            "modules": { "Core": { "path": ".", "main": false } }
+        });
+        let vars = HashMap::from([
+            (String::from("VCS__FORCE_SIGN"), String::from("true")),
+            (String::from("VCS__TAG_TEMPLATE"), String::from("v{{version}}")),
+            (String::from("MODULES__CORE__MAIN"), String::from("true")),
+            (String::from("TOKEN"), String::from("secret")),
+        ]);
+
+        let applied = apply_env_overrides(&mut config, &vars).expect("Error applying overrides");
+        assert!(applied.contains(&(String::from("modules.Core.main"), String::from("PANRELEASE_MODULES__CORE__MAIN"))));
+        assert_eq!(json!({
+            "vcs": { "software": "Git", "tag_template": "v{{version}}", "force_sign": true },
+            "modules": { "Core": { "path": ".", "main": true } }
+        }), config);
+    }
+
+    #[test]
+    fn create_missing_tables() {
+        let mut config = json!({ "modules": {} });
+        let vars = HashMap::from([(String::from("MODULES__CLI__PATH"), String::from("cli"))]);
+
+        let applied = apply_env_overrides(&mut config, &vars).expect("Error applying overrides");
+        assert_eq!(vec![(String::from("modules.cli.path"), String::from("PANRELEASE_MODULES__CLI__PATH"))], applied);
+        assert_eq!(json!({ "modules": { "cli": { "path": "cli" } } }), config);
+    }
+
+    #[test]
+    fn reject_override_below_scalar() {
+        let mut config = json!({ "vcs": { "software": "Git" } });
+        let vars = HashMap::from([(String::from("VCS__SOFTWARE__NAME"), String::from("Git"))]);
+        assert!(apply_env_overrides(&mut config, &vars).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::RootSchema;
+use serde_json::Value;
+
+use crate::project::config::PanProjectConfig;
+
+/// Key of a configuration layer that no configuration type defines
+#[derive(Debug, PartialEq)]
+pub struct UnknownKey {
+    /// Dotted path of the table holding the key, empty for the top level
+    pub table: String,
+    pub key: String,
+    /// Keys accepted in the table
+    pub expected: Vec<String>,
+}
+
+/// JSON Schema of `.panproject.toml`, generated from the configuration types
+pub fn generate() -> anyhow::Result<String> {
+    let mut schema = root_schema();
+    schema.schema.metadata().title = Some(String::from("panrelease configuration"));
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+fn root_schema() -> RootSchema {
+    SchemaSettings::draft07().into_generator().into_root_schema_for::<PanProjectConfig<()>>()
+}
+
+/// Keys of `layer` unknown to the configuration schema. Only the structure is checked, a layer of
+/// an `extends` chain may leave required values to the others.
+pub fn unknown_keys(layer: &Value) -> anyhow::Result<Vec<UnknownKey>> {
+    let schema = serde_json::to_value(root_schema())?;
+    let mut unknown = Vec::new();
+    walk(layer, &[&schema], &schema["definitions"], "", &mut unknown);
+    Ok(unknown)
+}
+
+fn walk<'a>(value: &Value, schemas: &[&'a Value], definitions: &'a Value, path: &str, unknown: &mut Vec<UnknownKey>) {
+    let mut candidates = Vec::new();
+    for schema in schemas {
+        expand(schema, definitions, &mut candidates);
+    }
+    match value {
+        Value::Array(items) => {
+            let item_schemas = candidates.iter().filter_map(|s| s.get("items")).collect::<Vec<_>>();
+            for (idx, item) in items.iter().enumerate() {
+                walk(item, &item_schemas, definitions, &format!("{path}[{idx}]"), unknown);
+            }
+        }
+        Value::Object(table) => {
+            // tables keyed by name, such as `modules`, accept any key
+            let entry_schemas = candidates.iter()
+                .filter_map(|s| s.get("additionalProperties"))
+                .filter(|s| s.is_object())
+                .collect::<Vec<_>>();
+            if !entry_schemas.is_empty() {
+                for (key, value) in table {
+                    walk(value, &entry_schemas, definitions, &join_key(path, key), unknown);
+                }
+                return;
+            }
+            // variants of the same table, e.g. package managers, are checked against all their keys
+            let mut properties = BTreeMap::<&str, Vec<&Value>>::new();
+            for schema in &candidates {
+                for (key, property) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+                    properties.entry(key).or_default().push(property);
+                }
+            }
+            if properties.is_empty() {
+                return;
+            }
+            for (key, value) in table {
+                match properties.get(key.as_str()) {
+                    Some(property_schemas) => walk(value, property_schemas, definitions, &join_key(path, key), unknown),
+                    None => unknown.push(UnknownKey {
+                        table: path.to_string(),
+                        key: key.clone(),
+                        expected: properties.keys().map(|k| k.to_string()).collect(),
+                    }),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolve references and subschemas into the schemas a value may match
+fn expand<'a>(schema: &'a Value, definitions: &'a Value, candidates: &mut Vec<&'a Value>) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(definition) = reference.strip_prefix("#/definitions/").and_then(|name| definitions.get(name)) {
+            expand(definition, definitions, candidates);
+        }
+        return;
+    }
+    candidates.push(schema);
+    for subschemas in ["allOf", "anyOf", "oneOf"] {
+        for subschema in schema.get(subschemas).and_then(Value::as_array).into_iter().flatten() {
+            expand(subschema, definitions, candidates);
+        }
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn schema_describes_modules() {
+        let schema: serde_json::Value = serde_json::from_str(&generate().expect("Error generating schema"))
+            .expect("Invalid schema json");
+        let module = &schema["definitions"]["ProjectModule"];
+        assert_eq!("string", module["properties"]["path"]["type"]);
+        assert_eq!(2, module["oneOf"].as_array().map(|v| v.len()).unwrap_or_default());
+        assert_eq!("{{version}}", schema["definitions"]["VcsConfig"]["oneOf"][0]["properties"]["tag_template"]["default"]);
+    }
+
+    #[test]
+    fn report_unknown_keys_of_partial_layer() {
+        let layer = serde_json::json!({
+            "vcs": { "tag_templte": "v{{version}}" },
+            "modules": {
+                "web": { "packageManager": "Npm" },
+                "app": { "path": "app", "packageManager": "Android", "version_cod": "increment" }
+            }
+        });
+
+        let unknown = unknown_keys(&layer).expect("Error checking keys");
+        assert_eq!(
+            vec![("modules.app", "version_cod"), ("vcs", "tag_templte")],
+            unknown.iter().map(|u| (u.table.as_str(), u.key.as_str())).collect::<Vec<_>>()
+        );
+        assert!(unknown[0].expected.contains(&String::from("version_code")));
+    }
+}
@@ -1,32 +1,44 @@
 use std::ffi::OsString;
-use anyhow::{bail, Context};
+use anyhow::Context;
 use clap::error::ErrorKind;
 use clap::Parser;
 use futures::executor::block_on;
-use crate::args::{Commands, PanReleaseArgs};
+use crate::args::{Commands, ConfigArgs, ConfigCommands, PanReleaseArgs};
 use crate::conf::loader::ConfigLoader;
-use crate::system::FileSystem;
+use crate::conf::schema;
+use crate::system::{EnvVars, FileSystem};
 
 pub fn run<I, T, S>(args: I) -> anyhow::Result<()>
     where
         I: IntoIterator<Item = T>,
         T: Into<OsString> + Clone,
-        S: FileSystem + 'static,
+        S: FileSystem + EnvVars + 'static,
 {
     let opts = match PanReleaseArgs::try_parse_from(args) {
         Ok(opts) => opts,
         Err(err) => err.exit(),
     };
 
+    if let Commands::Config(ConfigArgs { subcommand: ConfigCommands::Schema }) = opts.subcommand {
+        println!("{}", schema::generate()?);
+        return Ok(());
+    }
+
     let project = ConfigLoader::parse_config::<S>(opts.path)
         .context("Error parsing configuration file")?;
 
     match opts.subcommand {
         Commands::Release(rel_args) => {
-            if let Err(err) = project.release(rel_args) {
-                bail!("Error releasing project - {err}");
-            }
+            project.release(rel_args)
+                .context("Error releasing project")?;
+        }
+        Commands::Show => {
+            project.show()?;
+        }
+        Commands::Config(ConfigArgs { subcommand: ConfigCommands::Show }) => {
+            project.show_config();
         }
+        Commands::Config(_) => {}
     }
     Ok(())
 }
\ No newline at end of file
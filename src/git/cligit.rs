@@ -1,7 +1,9 @@
+use crate::git::{find_git_root, NO_IDENTITY};
 use crate::project::config::GitConfig;
 use crate::runner::CmdRunner;
 use crate::system::FileSystem;
-use anyhow::anyhow;
+use crate::vcs;
+use crate::vcs::{Change, VcsBackend};
 use std::path::{Path, PathBuf};
 
 pub struct GitRepo {
@@ -13,82 +15,114 @@ impl GitRepo {
     pub fn open<F: FileSystem>(config: GitConfig, path: &Path) -> anyhow::Result<Self> {
         Ok(Self {
             config,
-            path: Self::find_git_root::<F>(path)?.to_path_buf(),
+            path: find_git_root::<F>(path)?.to_path_buf(),
         })
     }
 
-    pub fn find_git_root<F: FileSystem>(path: &Path) -> anyhow::Result<&Path> {
-        let mut current = path;
-        loop {
-            if F::is_a_dir(&current.join(".git")) {
-                break Ok(current);
-            } else {
-                current = current.parent().ok_or(anyhow!("Could not find repo dir"))?;
-            }
+    /// `-c` options overriding the identity and signing configuration of git
+    fn config_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(identity) = &self.config.identity {
+            options.extend([String::from("-c"), format!("user.name={}", identity.name)]);
+            options.extend([String::from("-c"), format!("user.email={}", identity.email)]);
         }
+        if let Some(format) = self.config.signing_format {
+            options.extend([String::from("-c"), format!("gpg.format={}", format.name())]);
+        }
+        if let Some(key) = &self.config.signing_key {
+            options.extend([String::from("-c"), format!("user.signingkey={key}")]);
+        }
+        options
     }
 
-    pub fn is_staging_clean(&self) -> anyhow::Result<bool> {
-        let mut runner = CmdRunner::build(
-            "git",
-            &[String::from("status"), String::from("--porcelain=v1")],
-            &self.path,
-        )?;
-        let out = runner.output().and_then(|b| Ok(String::from_utf8(b)?))?;
-        let pending = out
-            .split('\n')
-            .map(|head| head.trim())
-            .filter(|head| !head.is_empty() && !(*head).starts_with("??"))
-            .collect::<Vec<_>>();
+    fn git_output(&self, args: &[&str]) -> anyhow::Result<String> {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let out = CmdRunner::build("git", &args, &self.path)?.output()?;
+        Ok(String::from_utf8(out)?)
+    }
+}
 
-        Ok(pending.is_empty())
+impl VcsBackend for GitRepo {
+    fn status(&self) -> anyhow::Result<Vec<Change>> {
+        // NUL separated entries keep paths unquoted, renames are reported as a deletion and an addition as in libgit2
+        let out = self.git_output(&["status", "--porcelain=v1", "-z", "--no-renames", "--untracked-files=no"])?;
+        Ok(out.split('\0')
+            .filter(|entry| entry.len() > 3)
+            .map(|entry| Change {
+                status: entry[..2].to_string(),
+                path: entry[3..].to_string(),
+            })
+            .collect())
     }
 
-    pub fn update_and_commit(&self, version: semver::Version) -> anyhow::Result<()> {
-        CmdRunner::build(
-            "git",
-            &[String::from("add"), String::from("-u")],
-            &self.path,
-        )?
-        .run()?;
+    fn untracked(&self) -> anyhow::Result<Vec<String>> {
+        let out = self.git_output(&["ls-files", "-z", "--others", "--exclude-standard"])?;
+        Ok(out.split('\0')
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn commit(&self, message: &str, paths: &[String]) -> anyhow::Result<()> {
+        vcs::check_commit_paths(paths)?;
+        if self.config.identity.is_none() && self.git_output(&["var", "GIT_COMMITTER_IDENT"]).is_err() {
+            anyhow::bail!("{NO_IDENTITY}");
+        }
 
-        let descr = version.to_string();
-        let commit_args = if self.config.force_sign {
-            vec! [String::from("commit"), String::from("-S"), String::from("-m"), descr]
-        } else {
-            vec! [String::from("commit"), String::from("-m"), descr]
-        };
+        let mut add_args = vec![String::from("add"), String::from("--")];
+        add_args.extend(paths.iter().cloned());
+        CmdRunner::build("git", &add_args, &self.path)?.run()?;
+
+        // committing the paths only leaves out changes staged before the release
+        let mut commit_args = self.config_options();
+        commit_args.push(String::from("commit"));
+        if self.config.signs_commits() {
+            commit_args.push(String::from("-S"));
+        }
+        commit_args.extend([String::from("-m"), message.to_string(), String::from("--")]);
+        commit_args.extend(paths.iter().cloned());
 
         CmdRunner::build(
             "git",
             &commit_args,
             &self.path,
         )?
-        .run()?;
-
-        let tag_descr = self
-            .config
-            .tag_template
-            .replace("{{version}}", &version.to_string());
+        .run()
+    }
 
-        if self.config.force_sign {
-            CmdRunner::build(
-                "git",
-                &[
-                    String::from("tag"),
-                    String::from("-a"),
-                    tag_descr.clone(),
-                    String::from("-m"),
-                    tag_descr,
-                    String::from("-s"),
-                ],
-                &self.path,
-            )?
-            .run()?;
-        } else {
-            CmdRunner::build("git", &[String::from("tag"), tag_descr], &self.path)?.run()?;
+    fn tag(&self, name: &str, message: Option<&str>) -> anyhow::Result<()> {
+        // signed tags are annotated, the tag name is the message when none is given
+        let message = message.or(self.config.signs_tags().then_some(name));
+        let mut tag_args = self.config_options();
+        tag_args.push(String::from("tag"));
+        match message {
+            Some(message) => tag_args.extend([String::from("-a"), name.to_string(), String::from("-m"), message.to_string()]),
+            None => tag_args.push(name.to_string()),
+        }
+        if self.config.signs_tags() {
+            tag_args.push(String::from("-s"));
         }
+        CmdRunner::build("git", &tag_args, &self.path)?.run()
+    }
+
+    fn log(&self, limit: usize) -> anyhow::Result<Vec<String>> {
+        let out = self.git_output(&["log", &format!("--max-count={limit}"), "--format=%s"])?;
+        Ok(out.lines().map(String::from).collect())
+    }
+
+    fn tags(&self) -> anyhow::Result<Vec<String>> {
+        let out = self.git_output(&["tag", "--list"])?;
+        Ok(out.lines().map(String::from).collect())
+    }
+
+    fn branch(&self) -> anyhow::Result<Option<String>> {
+        let out = self.git_output(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        Ok(Some(out.trim().to_string()).filter(|branch| branch != "HEAD"))
+    }
 
-        Ok(())
+    fn push(&self, tags: &[String]) -> anyhow::Result<()> {
+        let mut push_args = vec![String::from("push"), String::from("origin"), String::from("HEAD")];
+        push_args.extend(tags.iter().map(|tag| format!("refs/tags/{tag}")));
+        CmdRunner::build("git", &push_args, &self.path)?.run()
     }
 }
@@ -1,8 +1,12 @@
 use std::path::Path;
 use anyhow::anyhow;
-use git2::{Repository, RepositoryOpenFlags, StatusOptions};
+use git2::{Cred, CredentialType, Index, ObjectType, Oid, PushOptions, RemoteCallbacks, Repository, Signature, Status, StatusOptions};
+use crate::git::{find_git_root, NO_IDENTITY};
 use crate::project::config::GitConfig;
+use crate::runner::CmdRunner;
 use crate::system::FileSystem;
+use crate::vcs;
+use crate::vcs::{Change, VcsBackend};
 
 pub struct GitRepo {
     config: GitConfig,
@@ -13,55 +17,234 @@ impl GitRepo {
     pub fn open<F: FileSystem>(config: GitConfig, path: &Path) -> anyhow::Result<Self> {
         Ok(Self {
             config,
-            repo: Repository::open_ext(path, RepositoryOpenFlags::empty(), [path])?
+            repo: Repository::open(find_git_root::<F>(path)?)?
         })
     }
+}
 
-    pub fn find_git_root<F: FileSystem>(path: &Path) -> anyhow::Result<&Path> {
-        let mut current = path;
-        loop {
-            if F::is_a_dir(&current.join(".git")) {
-                break Ok(current);
-            } else {
-                current = current.parent()
-                    .ok_or(anyhow!("Could not find repo dir"))?;
-            }
-        }
-    }
-
-    pub fn is_staging_clean(&self) -> anyhow::Result<bool> {
+impl VcsBackend for GitRepo {
+    fn status(&self) -> anyhow::Result<Vec<Change>> {
         let mut opts = StatusOptions::new();
         opts
             .include_unmodified(false)
             .include_untracked(false)
             .include_ignored(false);
 
-        Ok(self.repo.statuses(Some(&mut opts))?.is_empty())
+        Ok(self.repo.statuses(Some(&mut opts))?
+            .iter()
+            .map(|entry| Change {
+                status: status_code(entry.status()),
+                path: String::from_utf8_lossy(entry.path_bytes()).to_string(),
+            })
+            .collect())
     }
 
-    pub fn update_and_commit(&self, version: semver::Version) -> anyhow::Result<()> {
-        if self.config.force_sign {
-            anyhow::bail!("Commit/tag sign is not supported in lib mode...");
-        }
+    fn untracked(&self) -> anyhow::Result<Vec<String>> {
+        let mut opts = StatusOptions::new();
+        opts
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+
+        Ok(self.repo.statuses(Some(&mut opts))?
+            .iter()
+            .filter(|entry| entry.status().is_wt_new())
+            .map(|entry| String::from_utf8_lossy(entry.path_bytes()).to_string())
+            .collect())
+    }
+
+    fn commit(&self, message: &str, paths: &[String]) -> anyhow::Result<()> {
+        vcs::check_commit_paths(paths)?;
+        let signature = self.signature()?;
+        let workdir = self.repo.workdir().ok_or_else(|| anyhow!("Cannot release in a bare repository"))?;
+        let parent_commit = self.repo.head()?.peel_to_commit()?;
 
+        // the release tree is HEAD with the given paths only, leaving out changes staged before the release
         let mut index = self.repo.index()?;
-        index.update_all(["*"].iter(), Some(&mut (|name, _content| {
-            log::debug!("Adding {:?}", name);
-            0
-        })))?;
+        let mut release_index = Index::new()?;
+        release_index.read_tree(&parent_commit.tree()?)?;
+        for path in paths.iter().map(Path::new) {
+            log::debug!("Adding {:?}", path);
+            if workdir.join(path).symlink_metadata().is_ok() {
+                index.add_path(path)?;
+                let entry = index.get_path(path, 0).ok_or_else(|| anyhow!("{} not found in the index", path.display()))?;
+                release_index.add(&entry)?;
+            } else {
+                index.remove_path(path)?;
+                release_index.remove_path(path)?;
+            }
+        }
         index.write()?;
 
-        let signature = self.repo.signature()?;
-        let oid = index.write_tree()?;
+        let oid = release_index.write_tree_to(&self.repo)?;
         let tree = self.repo.find_tree(oid)?;
-        let parent_commit = self.repo.head()?.peel_to_commit()?;
 
-        let descr = version.to_string();
-        let commit_oid = self.repo.commit(Some("HEAD"), &signature, &signature, &descr, &tree, &[&parent_commit])?;
+        if self.config.signs_commits() {
+            let buffer = self.repo.commit_create_buffer(&signature, &signature, message, &tree, &[&parent_commit])?;
+            let content = buffer.as_str().ok_or_else(|| anyhow!("Commit buffer is not valid utf-8"))?;
+            let commit_signature = self.sign(content)?;
+            let commit_oid = self.repo.commit_signed(content, &commit_signature, Some("gpgsig"))?;
+            self.repo.head()?.set_target(commit_oid, &format!("commit: {message}"))?;
+        } else {
+            self.repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[&parent_commit])?;
+        }
+        Ok(())
+    }
+
+    fn tag(&self, name: &str, message: Option<&str>) -> anyhow::Result<()> {
+        let commit = self.repo.head()?.peel_to_commit()?;
+        if self.config.signs_tags() {
+            // signed tags are annotated, the tag name is the message when none is given
+            let signature = self.signature()?;
+            self.signed_tag(name, commit.id(), &signature, message.unwrap_or(name))?;
+        } else if let Some(message) = message {
+            let signature = self.signature()?;
+            self.repo.tag(name, commit.as_object(), &signature, message, false)?;
+        } else {
+            self.repo.tag_lightweight(name, commit.as_object(), false)?;
+        }
+        Ok(())
+    }
+
+    fn log(&self, limit: usize) -> anyhow::Result<Vec<String>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk
+            .take(limit)
+            .map(|oid| {
+                let commit = self.repo.find_commit(oid?)?;
+                Ok(commit.summary().unwrap_or_default().to_string())
+            })
+            .collect()
+    }
+
+    fn tags(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.repo.tag_names(None)?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect())
+    }
+
+    fn branch(&self) -> anyhow::Result<Option<String>> {
+        let head = self.repo.head()?;
+        Ok(head.is_branch()
+            .then(|| head.shorthand().map(String::from))
+            .flatten())
+    }
+
+    fn push(&self, tags: &[String]) -> anyhow::Result<()> {
+        let head = self.repo.head()?;
+        let branch = head.name().ok_or_else(|| anyhow!("HEAD is not a valid reference"))?;
+        let mut refspecs = vec![format!("{branch}:{branch}")];
+        refspecs.extend(tags.iter().map(|tag| format!("refs/tags/{tag}:refs/tags/{tag}")));
+
+        let config = self.repo.config()?;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed| {
+            if allowed.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                Cred::credential_helper(&config, url, username)
+            } else {
+                Cred::default()
+            }
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+
+        self.repo.find_remote("origin")?.push(&refspecs, Some(&mut options))?;
+        Ok(())
+    }
+}
 
-        let commit_obj = self.repo.find_object(commit_oid, None)?;
-        self.repo.tag_lightweight(&descr, &commit_obj, false)?;
+impl GitRepo {
+    /// Identity recorded in commits and tags, the configured one or the one of the git configuration
+    fn signature(&self) -> anyhow::Result<Signature<'static>> {
+        match &self.config.identity {
+            Some(identity) => Ok(Signature::now(&identity.name, &identity.email)?),
+            None => self.repo.signature().map_err(|_| anyhow!(NO_IDENTITY)),
+        }
+    }
+
+    /// libgit2 cannot sign tags: write the tag object with the signature appended to its message, as git does
+    fn signed_tag(&self, name: &str, target: Oid, tagger: &Signature, message: &str) -> anyhow::Result<()> {
+        let when = tagger.when();
+        let offset = when.offset_minutes();
+        let mut content = format!(
+            "object {target}\ntype commit\ntag {name}\ntagger {} <{}> {} {}{:02}{:02}\n\n{}\n",
+            tagger.name().unwrap_or_default(),
+            tagger.email().unwrap_or_default(),
+            when.seconds(),
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 60,
+            offset.abs() % 60,
+            message.trim_end(),
+        );
+        content.push_str(&self.sign(&content)?);
 
+        let tag_oid = self.repo.odb()?.write(ObjectType::Tag, content.as_bytes())?;
+        self.repo.reference(&format!("refs/tags/{name}"), tag_oid, false, &format!("tag: {name}"))?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Sign the buffer with the program configured in git, following `gpg.format` unless overridden in the configuration
+    fn sign(&self, content: &str) -> anyhow::Result<String> {
+        let config = self.repo.config()?;
+        let format = match self.config.signing_format {
+            Some(format) => format.name().to_string(),
+            None => config.get_string("gpg.format").unwrap_or_else(|_| String::from("openpgp")),
+        };
+        let signing_key = self.config.signing_key.clone()
+            .or_else(|| config.get_string("user.signingkey").ok());
+        let workdir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+
+        let mut runner = match format.as_str() {
+            "openpgp" | "x509" => {
+                let (program_key, default_program) = if format == "x509" { ("gpg.x509.program", "gpgsm") } else { ("gpg.program", "gpg") };
+                let program = config.get_string(program_key).unwrap_or_else(|_| String::from(default_program));
+                let mut args = vec![String::from("--status-fd=2"), String::from("-bsa")];
+                if let Some(key) = signing_key {
+                    args.extend([String::from("-u"), key]);
+                }
+                CmdRunner::build(&program, &args, workdir)?
+            }
+            "ssh" => {
+                let program = config.get_string("gpg.ssh.program").unwrap_or_else(|_| String::from("ssh-keygen"));
+                let key = signing_key.ok_or_else(|| anyhow!("user.signingkey is required to sign with ssh"))?;
+                let args = [String::from("-Y"), String::from("sign"), String::from("-n"), String::from("git"), String::from("-f"), key];
+                CmdRunner::build(&program, &args, workdir)?
+            }
+            other => anyhow::bail!("Unsupported gpg.format {other}"),
+        };
+        let signature = runner.output_with_input(content.as_bytes())?;
+        Ok(String::from_utf8(signature)?)
+    }
+}
+
+/// Two letters index and worktree code of `git status --porcelain`
+fn status_code(status: Status) -> String {
+    if status.is_conflicted() {
+        return String::from("UU");
+    }
+    let index = [
+        (Status::INDEX_NEW, 'A'),
+        (Status::INDEX_MODIFIED, 'M'),
+        (Status::INDEX_DELETED, 'D'),
+        (Status::INDEX_RENAMED, 'R'),
+        (Status::INDEX_TYPECHANGE, 'T'),
+    ];
+    let worktree = [
+        (Status::WT_MODIFIED, 'M'),
+        (Status::WT_DELETED, 'D'),
+        (Status::WT_RENAMED, 'R'),
+        (Status::WT_TYPECHANGE, 'T'),
+    ];
+    [&index[..], &worktree[..]]
+        .iter()
+        .map(|codes| codes.iter()
+            .find(|(flag, _)| status.contains(*flag))
+            .map(|(_, code)| *code)
+            .unwrap_or(' '))
+        .collect()
+}
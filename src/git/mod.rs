@@ -1,10 +1,298 @@
-mod cligit;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::project::config::{GitBackend, GitConfig};
+use crate::system::FileSystem;
+use crate::vcs::VcsBackend;
 
-#[cfg(not(feature = "git2"))]
-pub use cligit::GitRepo;
+const GITDIR_PREFIX: &str = "gitdir:";
+const NO_IDENTITY: &str = "No git identity found, set user.name and user.email in the git configuration or name and email in [vcs.identity]";
+
+mod cligit;
 
 #[cfg(feature = "git2")]
 mod libgit;
 
-#[cfg(feature = "git2")]
-pub use libgit::GitRepo;
\ No newline at end of file
+/// Open the git backend selected by the configuration
+pub fn open<F: FileSystem>(config: GitConfig, path: &Path) -> anyhow::Result<Box<dyn VcsBackend>> {
+    match config.backend {
+        GitBackend::Cli => Ok(Box::new(cligit::GitRepo::open::<F>(config, path)?)),
+        #[cfg(feature = "git2")]
+        GitBackend::Libgit2 => Ok(Box::new(libgit::GitRepo::open::<F>(config, path)?)),
+        #[cfg(not(feature = "git2"))]
+        GitBackend::Libgit2 => anyhow::bail!("panrelease was built without libgit2 support, enable the libgit feature or use backend = \"cli\""),
+    }
+}
+
+/// Nearest working tree root from `path` upwards
+pub fn find_git_root<F: FileSystem>(path: &Path) -> anyhow::Result<&Path> {
+    for dir in path.ancestors() {
+        if git_dir::<F>(dir)?.is_some() {
+            return Ok(dir);
+        }
+    }
+    Err(anyhow!("Could not find repo dir"))
+}
+
+/// Repository of the working tree rooted in `dir`, if any. In linked worktrees and submodules
+/// `.git` is a file pointing to the repository with `gitdir: <path>`, relative to the working tree
+pub fn git_dir<F: FileSystem>(dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let dot_git = dir.join(".git");
+    if F::is_a_dir(&dot_git) {
+        return Ok(Some(dot_git));
+    }
+    if !F::is_a_file(&dot_git) {
+        return Ok(None);
+    }
+    let content = F::read_string(&dot_git)?;
+    let target = content.strip_prefix(GITDIR_PREFIX)
+        .map(|target| dir.join(target.trim()))
+        .ok_or_else(|| anyhow!("{} is not a gitdir file", dot_git.display()))?;
+    if !F::is_a_dir(&target) {
+        anyhow::bail!("{} points to the missing repository {}", dot_git.display(), target.display());
+    }
+    Ok(Some(target))
+}
+
+/// Behaviour shared by every backend, run against temporary repositories
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use crate::project::config::GitConfig;
+    use crate::project::hooks::HookContext;
+    use crate::test_utils::{TempDir, TempRepo};
+    use crate::vcs::{Change, VcsBackend};
+
+    /// Repository with `version.txt` at 0.1.0 committed
+    pub fn init_repo() -> TempRepo {
+        let repo = TempRepo::new("git");
+        repo.write("version.txt", "0.1.0\n");
+        repo.commit_all("init");
+        repo
+    }
+
+    /// Configure ssh signing with a fresh key, `None` when ssh-keygen is not available
+    pub fn ssh_signing(repo: &TempRepo) -> Option<()> {
+        let key = ssh_key(repo)?;
+        repo.git(&["config", "gpg.format", "ssh"]);
+        repo.git(&["config", "user.signingkey", key.to_str()?]);
+        Some(())
+    }
+
+    /// Fresh ssh key trusted to verify signatures, without configuring git to sign with it
+    pub fn ssh_key(repo: &TempRepo) -> Option<PathBuf> {
+        let key = repo.path().join(".git").join("release_key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "panrelease@example.com", "-f"])
+            .arg(&key)
+            .status()
+            .ok()?;
+        if !status.success() {
+            return None;
+        }
+        let public_key = std::fs::read_to_string(key.with_extension("pub")).ok()?;
+        let allowed_signers = repo.path().join(".git").join("allowed_signers");
+        std::fs::write(&allowed_signers, format!("panrelease@example.com {public_key}")).ok()?;
+        repo.git(&["config", "gpg.ssh.allowedSignersFile", allowed_signers.to_str()?]);
+        Some(key)
+    }
+
+    pub fn context(version: &str) -> HookContext {
+        let version = semver::Version::parse(version).expect("Invalid version");
+        HookContext {
+            module: String::from("core"),
+            tag: format!("v{version}"),
+            modules: vec![(String::from("core"), version.clone())],
+            previous_version: semver::Version::new(0, 1, 0),
+            version,
+        }
+    }
+
+    pub fn config(toml: &str) -> GitConfig {
+        toml::from_str(toml).expect("Error parsing git config")
+    }
+
+    macro_rules! backend_suite {
+        ($name:ident, $backend:ty) => {
+            mod $name {
+                use super::*;
+                use crate::system::NativeSystem;
+
+                fn release(repo: &TempRepo, conf: GitConfig, version: &str) {
+                    repo.write("version.txt", &format!("{version}\n"));
+                    let ctx = context(version);
+                    let tag_message = conf.annotated_tags().then(|| conf.tag_message(&ctx));
+                    let git_repo = <$backend>::open::<NativeSystem>(conf.clone(), repo.path()).expect("Error opening repo");
+                    git_repo.commit(&conf.commit_message(&ctx), &[String::from("version.txt")]).expect("Error committing release");
+                    git_repo.tag(&ctx.tag, tag_message.as_deref()).expect("Error tagging release");
+                }
+
+                #[test]
+                fn status_lists_tracked_changes() {
+                    let repo = init_repo();
+                    for file in ["a.txt", "b.txt", "old.txt", "with space.txt"] {
+                        repo.write(file, "content\n");
+                    }
+                    repo.commit_all("files");
+                    let git_repo = <$backend>::open::<NativeSystem>(GitConfig::default(), repo.path()).expect("Error opening repo");
+                    repo.write("untracked.txt", "new");
+                    assert_eq!(Vec::<Change>::new(), git_repo.status().expect("Error reading status"));
+
+                    repo.write("version.txt", "0.2.0\n");
+                    repo.write("with space.txt", "changed\n");
+                    repo.write("a.txt", "staged\n");
+                    repo.write("new.txt", "added\n");
+                    repo.git(&["add", "a.txt", "new.txt"]);
+                    repo.git(&["rm", "-q", "--cached", "b.txt"]);
+                    repo.git(&["mv", "old.txt", "moved.txt"]);
+                    let mut changes = git_repo.status().expect("Error reading status")
+                        .into_iter()
+                        .map(|change| change.to_string())
+                        .collect::<Vec<_>>();
+                    changes.sort_by(|a, b| a[3..].cmp(&b[3..]));
+                    assert_eq!(vec!["M  a.txt", "D  b.txt", "A  moved.txt", "A  new.txt", "D  old.txt", " M version.txt", " M with space.txt"], changes);
+                }
+
+                #[test]
+                fn commit_only_given_paths() {
+                    let repo = init_repo();
+                    for file in ["other.txt", "staged.txt", "removed.txt"] {
+                        repo.write(file, "content\n");
+                    }
+                    repo.write(".gitignore", "*.log\n");
+                    repo.commit_all("files");
+                    repo.write("version.txt", "0.2.0\n");
+                    repo.write("other.txt", "changed\n");
+                    repo.write("staged.txt", "staged\n");
+                    repo.git(&["add", "staged.txt"]);
+                    std::fs::remove_file(repo.path().join("removed.txt")).expect("Error removing file");
+                    repo.write("dist/bundle.js", "bundle\n");
+                    repo.write("build.log", "ignored\n");
+
+                    let git_repo = <$backend>::open::<NativeSystem>(GitConfig::default(), repo.path()).expect("Error opening repo");
+                    assert_eq!(vec![String::from("dist/bundle.js")], git_repo.untracked().expect("Error listing untracked files"));
+                    assert!(git_repo.commit("0.2.0", &[]).is_err(), "pending changes committed without paths");
+                    let paths = ["version.txt", "removed.txt", "dist/bundle.js"].map(String::from);
+                    git_repo.commit("0.2.0", &paths).expect("Error committing release");
+
+                    assert_eq!("A\tdist/bundle.js\nD\tremoved.txt\nM\tversion.txt\n", repo.git(&["show", "--format=", "--name-status", "HEAD"]));
+                    assert_eq!(" M other.txt\nM  staged.txt\n", repo.git(&["status", "--porcelain", "--untracked-files=no"]));
+                }
+
+                #[test]
+                fn lightweight_tag_from_template() {
+                    let repo = init_repo();
+                    release(&repo, config("tag_template = \"v{{version}}\"\n[trailers]\nRelease-Modules = \"{{modules}}\"\n"), "0.2.0");
+                    assert_eq!("0.2.0\n\nRelease-Modules: core@0.2.0", repo.git(&["log", "-1", "--format=%B"]).trim_end());
+                    assert_eq!("commit\n", repo.git(&["cat-file", "-t", "v0.2.0"]));
+                    assert_eq!("", repo.git(&["status", "--porcelain", "--untracked-files=no"]));
+                }
+
+                #[test]
+                fn annotated_tag_with_message() {
+                    let repo = init_repo();
+                    release(&repo, config("tag_template = \"v{{version}}\"\nannotated = true\ntag_message_template = \"Release {{version}}\"\n"), "0.2.0");
+                    assert_eq!("tag\n", repo.git(&["cat-file", "-t", "v0.2.0"]));
+                    assert_eq!("Release 0.2.0", repo.git(&["tag", "-l", "--format=%(contents)", "v0.2.0"]).trim_end());
+                }
+
+                #[test]
+                fn signed_commit_and_tag() {
+                    let repo = init_repo();
+                    if ssh_signing(&repo).is_none() {
+                        eprintln!("ssh-keygen not available, skipping");
+                        return;
+                    }
+                    release(&repo, config("tag_template = \"v{{version}}\"\nforce_sign = true\n"), "0.2.0");
+                    repo.git(&["verify-commit", "HEAD"]);
+                    repo.git(&["verify-tag", "v0.2.0"]);
+                }
+
+                #[test]
+                fn sign_tags_with_configured_key() {
+                    let repo = init_repo();
+                    let Some(key) = ssh_key(&repo) else {
+                        eprintln!("ssh-keygen not available, skipping");
+                        return;
+                    };
+                    let conf = format!("tag_template = \"v{{{{version}}}}\"\nsign_tags = true\nsigning_format = \"ssh\"\nsigning_key = {:?}\n", key.to_str().expect("Invalid path"));
+                    release(&repo, config(&conf), "0.2.0");
+                    assert_eq!("N\n", repo.git(&["log", "-1", "--format=%G?"]));
+                    repo.git(&["verify-tag", "v0.2.0"]);
+                }
+
+                #[test]
+                fn release_with_configured_identity() {
+                    let repo = init_repo();
+                    release(&repo, config("annotated = true\n[identity]\nname = \"Panrelease\"\nemail = \"release@example.com\"\n"), "0.2.0");
+                    assert_eq!("Panrelease <release@example.com> Panrelease <release@example.com>\n", repo.git(&["log", "-1", "--format=%an <%ae> %cn <%ce>"]));
+                    assert_eq!("Panrelease <release@example.com>\n", repo.git(&["tag", "-l", "--format=%(taggername) %(taggeremail)", "v0.2.0"]));
+                }
+
+                #[test]
+                fn read_log_tags_and_branch() {
+                    let repo = init_repo();
+                    repo.git(&["checkout", "-q", "-b", "release"]);
+                    release(&repo, config("tag_template = \"v{{version}}\""), "0.2.0");
+                    let git_repo = <$backend>::open::<NativeSystem>(GitConfig::default(), repo.path()).expect("Error opening repo");
+                    assert_eq!(vec![String::from("0.2.0"), String::from("init")], git_repo.log(5).expect("Error reading log"));
+                    assert_eq!(vec![String::from("v0.2.0")], git_repo.tags().expect("Error reading tags"));
+                    assert_eq!(Some(String::from("release")), git_repo.branch().expect("Error reading branch"));
+                    repo.git(&["checkout", "-q", "--detach"]);
+                    assert_eq!(None, git_repo.branch().expect("Error reading branch"));
+                }
+
+                #[test]
+                fn release_in_linked_worktree() {
+                    let repo = init_repo();
+                    let worktree_dir = TempDir::new("git-worktree");
+                    let worktree = worktree_dir.path().join("release");
+                    repo.git(&["worktree", "add", "-q", "-b", "release", worktree.to_str().expect("Invalid path")]);
+                    std::fs::write(worktree.join("version.txt"), "0.2.0\n").expect("Error writing file");
+
+                    let git_repo = <$backend>::open::<NativeSystem>(GitConfig::default(), &worktree).expect("Error opening worktree");
+                    assert_eq!(1, git_repo.status().expect("Error reading status").len());
+                    git_repo.commit("0.2.0", &[String::from("version.txt")]).expect("Error committing release");
+                    git_repo.tag("v0.2.0", None).expect("Error tagging release");
+
+                    assert_eq!("0.2.0\n", repo.git(&["log", "-1", "--format=%s", "release"]));
+                    assert_eq!(repo.git(&["rev-parse", "release"]), repo.git(&["rev-parse", "v0.2.0"]));
+                    assert_eq!("init\n", repo.git(&["log", "-1", "--format=%s", "HEAD"]));
+                }
+
+                #[test]
+                fn push_branch_and_tags() {
+                    let remote = TempRepo::bare("git-remote");
+                    let repo = init_repo();
+                    repo.git(&["remote", "add", "origin", remote.path().to_str().expect("Invalid path")]);
+                    release(&repo, config("tag_template = \"v{{version}}\""), "0.2.0");
+                    let git_repo = <$backend>::open::<NativeSystem>(GitConfig::default(), repo.path()).expect("Error opening repo");
+                    git_repo.push(&[String::from("v0.2.0")]).expect("Error pushing");
+                    assert_eq!(repo.git(&["rev-parse", "HEAD"]), remote.git(&["rev-parse", "v0.2.0^{commit}"]));
+                }
+            }
+        };
+    }
+
+    backend_suite!(cligit, crate::git::cligit::GitRepo);
+
+    #[test]
+    fn find_submodule_root() {
+        let module = init_repo();
+        let repo = init_repo();
+        let module_path = module.path().to_str().expect("Invalid path");
+        repo.git(&["-c", "protocol.file.allow=always", "submodule", "add", "-q", module_path, "libs/module"]);
+        let module_src = repo.path().join("libs/module/src");
+        std::fs::create_dir_all(&module_src).expect("Error creating dir");
+
+        let root = super::find_git_root::<crate::system::NativeSystem>(&module_src).expect("Error finding root");
+        assert_eq!(repo.path().join("libs/module"), root);
+        assert!(super::git_dir::<crate::system::NativeSystem>(root).expect("Invalid gitdir").is_some_and(|dir| dir.ends_with(".git/modules/libs/module")));
+    }
+
+    #[cfg(feature = "git2")]
+    backend_suite!(libgit, crate::git::libgit::GitRepo);
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::project::config::MercurialConfig;
+use crate::runner::CmdRunner;
+use crate::system::FileSystem;
+use crate::vcs;
+use crate::vcs::{Change, VcsBackend};
+
+pub struct HgRepo {
+    config: MercurialConfig,
+    path: PathBuf,
+}
+
+impl HgRepo {
+    pub fn open<F: FileSystem>(config: MercurialConfig, path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            config,
+            path: find_hg_root::<F>(path)?.to_path_buf(),
+        })
+    }
+
+    /// `--user` option overriding the committer of the mercurial configuration
+    fn user_options(&self) -> Vec<String> {
+        self.config.identity.iter()
+            .flat_map(|identity| [String::from("--user"), identity.to_string()])
+            .collect()
+    }
+
+    fn hg(&self, args: &[&str]) -> anyhow::Result<CmdRunner> {
+        self.hg_with(args, &[])
+    }
+
+    fn hg_with(&self, args: &[&str], options: &[String]) -> anyhow::Result<CmdRunner> {
+        let args = args.iter().map(|arg| arg.to_string()).chain(options.iter().cloned()).collect::<Vec<_>>();
+        let mut runner = CmdRunner::build("hg", &args, &self.path)?;
+        // stable output, unaffected by the user configuration
+        runner.envs(&[(String::from("HGPLAIN"), String::from("1"))]);
+        Ok(runner)
+    }
+
+    fn hg_output(&self, args: &[&str]) -> anyhow::Result<String> {
+        let out = self.hg(args)?.output()?;
+        Ok(String::from_utf8(out)?)
+    }
+}
+
+impl VcsBackend for HgRepo {
+    fn status(&self) -> anyhow::Result<Vec<Change>> {
+        // modified, added, removed and missing files, untracked ones are not reported as in git
+        let out = self.hg_output(&["status", "--modified", "--added", "--removed", "--deleted", "--print0"])?;
+        Ok(out.split('\0')
+            .filter(|entry| entry.len() > 2)
+            .map(|entry| Change {
+                status: entry[..1].to_string(),
+                path: entry[2..].to_string(),
+            })
+            .collect())
+    }
+
+    fn untracked(&self) -> anyhow::Result<Vec<String>> {
+        let out = self.hg_output(&["status", "--unknown", "--no-status", "--print0"])?;
+        Ok(out.split('\0')
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn commit(&self, message: &str, paths: &[String]) -> anyhow::Result<()> {
+        vcs::check_commit_paths(paths)?;
+        // listed files only, new ones being added and missing ones removed
+        let mut options = self.user_options();
+        options.push(String::from("--"));
+        options.extend(paths.iter().cloned());
+        self.hg_with(&["commit", "--addremove", "--message", message], &options)?.run()
+    }
+
+    fn tag(&self, name: &str, message: Option<&str>) -> anyhow::Result<()> {
+        match message {
+            Some(message) => self.hg_with(&["tag", "--message", message, name], &self.user_options())?.run(),
+            None => self.hg_with(&["tag", name], &self.user_options())?.run(),
+        }
+    }
+
+    fn log(&self, limit: usize) -> anyhow::Result<Vec<String>> {
+        let out = self.hg_output(&["log", "--limit", &limit.to_string(), "--template", "{desc|firstline}\\n"])?;
+        Ok(out.lines().map(String::from).collect())
+    }
+
+    fn tags(&self) -> anyhow::Result<Vec<String>> {
+        let out = self.hg_output(&["tags", "--template", "{tag}\\n"])?;
+        Ok(out.lines()
+            .filter(|tag| *tag != "tip")
+            .map(String::from)
+            .collect())
+    }
+
+    fn branch(&self) -> anyhow::Result<Option<String>> {
+        let out = self.hg_output(&["branch"])?;
+        Ok(Some(out.trim().to_string()).filter(|branch| !branch.is_empty()))
+    }
+
+    fn push(&self, _tags: &[String]) -> anyhow::Result<()> {
+        // tags are recorded in .hgtags, pushing the current revision publishes them
+        self.hg(&["push", "--rev", "."])?.run()
+    }
+}
+
+pub fn find_hg_root<F: FileSystem>(path: &Path) -> anyhow::Result<&Path> {
+    path.ancestors()
+        .find(|dir| F::is_a_dir(&dir.join(".hg")))
+        .ok_or(anyhow!("Could not find repo dir"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use crate::system::NativeSystem;
+    use crate::test_utils::TempDir;
+
+    use super::*;
+
+    /// Mercurial repository in a [`TempDir`] with an initial commit
+    struct HgTempRepo {
+        dir: TempDir,
+    }
+
+    impl HgTempRepo {
+        fn init(name: &str) -> Self {
+            let repo = Self { dir: TempDir::new(&format!("hg-{name}")) };
+            repo.hg(&["init"]);
+            repo.dir.write(".hg/hgrc", "[ui]\nusername = Release Bot <bot@example.com>\n");
+            repo.dir.write("version.txt", "0.1.0\n");
+            repo.hg(&["add", "version.txt"]);
+            repo.hg(&["commit", "-m", "init"]);
+            repo
+        }
+
+        fn hg(&self, args: &[&str]) -> String {
+            let out = Command::new("hg")
+                .args(args)
+                .env("HGPLAIN", "1")
+                .current_dir(self.dir.path())
+                .output()
+                .expect("Error running hg, is Mercurial installed?");
+            assert!(out.status.success(), "hg {args:?} failed: {}", String::from_utf8_lossy(&out.stderr));
+            String::from_utf8(out.stdout).expect("Invalid hg output")
+        }
+    }
+
+    fn config() -> MercurialConfig {
+        toml::from_str("tag_template = \"v{{version}}\"").expect("Error parsing hg config")
+    }
+
+    #[test]
+    #[ignore = "requires Mercurial, run with --ignored"]
+    fn status_ignores_untracked_files() {
+        let repo = HgTempRepo::init("status");
+        let hg_repo = HgRepo::open::<NativeSystem>(config(), repo.dir.path()).expect("Error opening repo");
+        repo.dir.write("untracked.txt", "new");
+        assert!(hg_repo.status().expect("Error reading status").is_empty());
+        repo.dir.write("version.txt", "0.2.0\n");
+        let changes = hg_repo.status().expect("Error reading status");
+        assert_eq!(vec![Change { path: String::from("version.txt"), status: String::from("M") }], changes);
+    }
+
+    #[test]
+    #[ignore = "requires Mercurial, run with --ignored"]
+    fn commit_and_tag_release() {
+        let repo = HgTempRepo::init("release");
+        let conf = config();
+        let hg_repo = HgRepo::open::<NativeSystem>(conf.clone(), repo.dir.path()).expect("Error opening repo");
+        repo.dir.write("version.txt", "0.2.0\n");
+        hg_repo.commit("0.2.0", &[String::from("version.txt")]).expect("Error committing release");
+        hg_repo.tag(&conf.tag_name(&semver::Version::new(0, 2, 0)), None).expect("Error tagging release");
+
+        assert_eq!(vec![String::from("v0.2.0")], hg_repo.tags().expect("Error reading tags"));
+        let log = hg_repo.log(2).expect("Error reading log");
+        assert!(log[0].starts_with("Added tag v0.2.0"), "unexpected tag commit {}", log[0]);
+        assert_eq!("0.2.0", log[1]);
+        assert_eq!("0.2.0", repo.hg(&["log", "-r", "v0.2.0", "--template", "{desc}"]));
+        assert_eq!(Some(String::from("default")), hg_repo.branch().expect("Error reading branch"));
+        assert!(hg_repo.status().expect("Error reading status").is_empty());
+    }
+}
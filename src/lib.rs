@@ -7,7 +7,11 @@ mod package;
 mod runner;
 mod parser;
 mod utils;
+#[cfg(test)]
+mod test_utils;
 mod git;
+mod hg;
+mod vcs;
 pub mod conf;
 
 use anyhow::Context;
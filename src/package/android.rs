@@ -0,0 +1,184 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use regex::Regex;
+use semver::Version;
+
+use crate::package::PanPackage;
+use crate::parser::arith;
+use crate::project::config::{AndroidConfig, VersionCodeStrategy};
+use crate::system::FileSystem;
+
+const CANDIDATE_FILES: [&str; 5] = [
+    "app/build.gradle.kts",
+    "app/build.gradle",
+    "build.gradle.kts",
+    "build.gradle",
+    "gradle.properties",
+];
+
+const VERSION_NAME_REGEX: &str = r#"(?m)(?P<version_prefix>^[ \t]*versionName[ \t]*(?:=[ \t]*)?["']?)(?P<version_value>\d[^"'\s]*)"#;
+const VERSION_CODE_REGEX: &str = r#"(?m)(?P<version_prefix>^[ \t]*versionCode[ \t]*(?:=[ \t]*)?)(?P<version_value>\d+)"#;
+
+pub struct AndroidPackage<F> {
+    file: PathBuf,
+    content: String,
+    version_code: VersionCodeStrategy,
+    filesystem: PhantomData<F>,
+}
+
+impl<F: FileSystem> AndroidPackage<F> {
+    pub fn new(path: PathBuf, conf: &AndroidConfig) -> anyhow::Result<Self> {
+        let file = Self::locate(&path, conf)
+            .ok_or_else(|| anyhow!("Could not find versionName in any gradle file of {path:?}"))?;
+        let content = F::read_string(&file)?;
+        Ok(Self {
+            file,
+            content,
+            version_code: conf.version_code.clone(),
+            filesystem: PhantomData,
+        })
+    }
+
+    /// Resolve the file holding `versionName`, either the configured one or the first candidate defining it.
+    pub fn locate(path: &Path, conf: &AndroidConfig) -> Option<PathBuf> {
+        if let Some(file) = &conf.file {
+            return Some(path.join(file)).filter(|f| F::is_a_file(f));
+        }
+        let version_regex = Regex::new(VERSION_NAME_REGEX).expect("Invalid regex");
+        CANDIDATE_FILES
+            .iter()
+            .map(|candidate| path.join(candidate))
+            .filter(|candidate| F::is_a_file(candidate))
+            .find(|candidate| {
+                F::read_string(candidate)
+                    .map(|content| version_regex.is_match(&content))
+                    .unwrap_or(false)
+            })
+    }
+
+    fn extract_version_code(&self) -> anyhow::Result<Option<u64>> {
+        let version_regex = Regex::new(VERSION_CODE_REGEX)?;
+        version_regex
+            .captures(&self.content)
+            .and_then(|cap| cap.name("version_value"))
+            .map(|code| Ok(code.as_str().parse::<u64>()?))
+            .transpose()
+    }
+
+    fn next_version_code(&self, version: &Version) -> anyhow::Result<u64> {
+        match &self.version_code {
+            VersionCodeStrategy::Increment => {
+                let current = self.extract_version_code()?
+                    .ok_or_else(|| anyhow!("Could not find versionCode in {:?}", self.file))?;
+                Ok(current + 1)
+            }
+            VersionCodeStrategy::Formula(formula) => {
+                let expr = formula
+                    .replace("{{major}}", &version.major.to_string())
+                    .replace("{{minor}}", &version.minor.to_string())
+                    .replace("{{patch}}", &version.patch.to_string());
+                let code = arith::eval(&expr)?;
+                u64::try_from(code).map_err(|_| anyhow!("versionCode formula produced a negative value ({code})"))
+            }
+        }
+    }
+}
+
+impl<F: FileSystem> PanPackage for AndroidPackage<F> {
+    fn extract_version(&self) -> anyhow::Result<Version> {
+        let version_regex = Regex::new(VERSION_NAME_REGEX)?;
+        let version_str = version_regex
+            .captures(&self.content)
+            .and_then(|cap| cap.name("version_value"))
+            .ok_or_else(|| anyhow!("Could not find versionName in {:?}", self.file))?
+            .as_str();
+
+        Ok(Version::parse(version_str)?)
+    }
+
+    fn set_version(&mut self, version: &Version) -> anyhow::Result<()> {
+        let version_code = self.next_version_code(version)?;
+
+        let version_name_regex = Regex::new(VERSION_NAME_REGEX)?;
+        let version_code_regex = Regex::new(VERSION_CODE_REGEX)?;
+        if !version_code_regex.is_match(&self.content) {
+            anyhow::bail!("Could not find versionCode in {:?}", self.file);
+        }
+
+        let content = version_name_regex
+            .replace(&self.content, format!("${{version_prefix}}{version}"))
+            .to_string();
+        self.content = version_code_regex
+            .replace(&content, format!("${{version_prefix}}{version_code}"))
+            .to_string();
+
+        Ok(())
+    }
+
+    fn persist(&self) -> anyhow::Result<()> {
+        F::write_string(&self.file, &self.content)?;
+        Ok(())
+    }
+
+    fn hook_after_rel(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::NativeSystem;
+
+    fn package(content: &str, version_code: VersionCodeStrategy) -> AndroidPackage<NativeSystem> {
+        AndroidPackage {
+            file: PathBuf::from("app/build.gradle"),
+            content: String::from(content),
+            version_code,
+            filesystem: PhantomData,
+        }
+    }
+
+    #[test]
+    fn bump_groovy_dsl() {
+        let mut pkg = package(
+            "android {\n    defaultConfig {\n        versionCode 41\n        versionName \"1.2.3\"\n    }\n}\n",
+            VersionCodeStrategy::Increment,
+        );
+        assert_eq!(Version::new(1, 2, 3), pkg.extract_version().unwrap());
+
+        pkg.set_version(&Version::new(1, 3, 0)).unwrap();
+        assert_eq!(
+            "android {\n    defaultConfig {\n        versionCode 42\n        versionName \"1.3.0\"\n    }\n}\n",
+            pkg.content
+        );
+    }
+
+    #[test]
+    fn bump_kotlin_dsl_with_formula() {
+        let mut pkg = package(
+            "android {\n    defaultConfig {\n        versionCode = 10203\n        versionName = \"1.2.3\"\n    }\n}\n",
+            VersionCodeStrategy::Formula(String::from("{{major}} * 10000 + {{minor}} * 100 + {{patch}}")),
+        );
+
+        pkg.set_version(&Version::new(2, 0, 1)).unwrap();
+        assert_eq!(
+            "android {\n    defaultConfig {\n        versionCode = 20001\n        versionName = \"2.0.1\"\n    }\n}\n",
+            pkg.content
+        );
+    }
+
+    #[test]
+    fn bump_gradle_properties() {
+        let mut pkg = package("versionCode=7\nversionName=0.1.0\n", VersionCodeStrategy::Increment);
+
+        pkg.set_version(&Version::new(0, 1, 1)).unwrap();
+        assert_eq!("versionCode=8\nversionName=0.1.1\n", pkg.content);
+    }
+}
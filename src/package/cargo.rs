@@ -27,7 +27,7 @@ impl <F: FileSystem> CargoPackage<F> {
 
 impl <F: FileSystem> PanPackage for CargoPackage<F> {
     fn extract_version(&self) -> anyhow::Result<Version> {
-        let Some(ver) = self.doc["package"]["version"].as_str() else {
+        let Some(ver) = self.doc.get("package").and_then(|p| p.get("version")).and_then(|v| v.as_str()) else {
             anyhow::bail!("cannot find version in Cargo.toml")
         };
         Ok(Version::from_str(ver)?)
@@ -48,4 +48,13 @@ impl <F: FileSystem> PanPackage for CargoPackage<F> {
         runner.run()?;
         Ok(())
     }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        // the lockfile of the workspace, in the package directory or above
+        self.path.ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|lockfile| F::is_a_file(lockfile))
+            .into_iter()
+            .collect()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use semver::Version;
+
+use crate::package::PanPackage;
+use crate::parser::FormatCodec;
+use crate::parser::json::JsonString;
+use crate::parser::tomlstring::TomlString;
+use crate::parser::xml::xmlstring::XmlString;
+use crate::project::config::{CustomConfig, FileFormat};
+use crate::system::FileSystem;
+
+pub struct CustomPackage<F> {
+    file: PathBuf,
+    version_path: String,
+    doc: Box<dyn FormatCodec>,
+    filesystem: PhantomData<F>,
+}
+
+impl<F: FileSystem> CustomPackage<F> {
+    pub fn new(path: PathBuf, conf: &CustomConfig) -> anyhow::Result<Self> {
+        let file = path.join(&conf.file);
+        let content = F::read_string(&file)?;
+        let doc: Box<dyn FormatCodec> = match conf.format {
+            FileFormat::Json => Box::new(JsonString::new(&content)),
+            FileFormat::Xml => Box::new(XmlString::new(&content)),
+            FileFormat::Toml => Box::new(TomlString::new(&content)?),
+        };
+        Ok(Self {
+            file,
+            version_path: conf.version_path.clone(),
+            doc,
+            filesystem: PhantomData,
+        })
+    }
+}
+
+impl<F: FileSystem> PanPackage for CustomPackage<F> {
+    fn extract_version(&self) -> anyhow::Result<Version> {
+        self.doc.extract(&self.version_path)?
+            .ok_or_else(|| anyhow!("Could not find {} in {:?}", self.version_path, self.file))
+            .and_then(|v| Ok(Version::parse(v)?))
+    }
+
+    fn set_version(&mut self, version: &Version) -> anyhow::Result<()> {
+        self.doc.replace(&self.version_path, &version.to_string())
+    }
+
+    fn persist(&self) -> anyhow::Result<()> {
+        F::write_string(&self.file, &self.doc.to_string())?;
+        Ok(())
+    }
+
+    fn hook_after_rel(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::system::NativeSystem;
+    use crate::test_utils::TempDir;
+
+    use super::*;
+
+    /// Bump 1.2.3 to 1.3.0 in `file` through a custom package, returning the persisted content
+    fn bump(file: &str, content: &str, conf: &str) -> String {
+        let dir = TempDir::new("custom");
+        dir.write(file, content);
+        let conf: CustomConfig = toml::from_str(conf).expect("Error parsing custom config");
+
+        let mut package = CustomPackage::<NativeSystem>::new(dir.path().to_path_buf(), &conf).expect("Error reading package");
+        assert_eq!(Version::new(1, 2, 3), package.extract_version().expect("Error extracting version"));
+        package.set_version(&Version::new(1, 3, 0)).expect("Error setting version");
+        package.persist().expect("Error persisting package");
+        dir.read(file)
+    }
+
+    #[test]
+    fn bump_json_nested_key() {
+        let persisted = bump(
+            "deno.json",
+            "{\n  \"name\": \"demo\",\n  \"meta\": { \"version\": \"1.2.3\" }\n}\n",
+            "file = \"deno.json\"\nformat = \"json\"\nversion_path = \"meta.version\"",
+        );
+        assert_eq!("{\n  \"name\": \"demo\",\n  \"meta\": { \"version\": \"1.3.0\" }\n}\n", persisted);
+    }
+
+    #[test]
+    fn bump_xml_element() {
+        let persisted = bump(
+            "plugin.xml",
+            "<plugin>\n  <name>demo</name>\n  <info><version>1.2.3</version></info>\n</plugin>\n",
+            "file = \"plugin.xml\"\nformat = \"xml\"\nversion_path = \"plugin/info/version\"",
+        );
+        assert_eq!("<plugin>\n  <name>demo</name>\n  <info><version>1.3.0</version></info>\n</plugin>\n", persisted);
+    }
+
+    #[test]
+    fn report_missing_version_path() {
+        let dir = TempDir::new("custom-missing");
+        dir.write("Project.toml", "name = \"demo\"\n");
+        let conf: CustomConfig = toml::from_str("file = \"Project.toml\"\nformat = \"toml\"\nversion_path = \"version\"").unwrap();
+        let package = CustomPackage::<NativeSystem>::new(dir.path().to_path_buf(), &conf).expect("Error reading package");
+        let err = package.extract_version().expect_err("Missing version extracted");
+        assert!(err.to_string().starts_with("Could not find version in"), "unexpected error {err}");
+    }
+}
@@ -59,4 +59,8 @@ impl<F: FileSystem> PanPackage for GradlePackage<F> {
     fn hook_after_rel(&self) -> anyhow::Result<()> {
         Ok(())
     }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
@@ -70,4 +70,8 @@ impl <F: FileSystem> PanPackage for MavenPackage<F> {
     fn hook_after_rel(&self) -> anyhow::Result<()> {
         Ok(())
     }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
\ No newline at end of file
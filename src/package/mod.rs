@@ -1,4 +1,8 @@
+use std::path::PathBuf;
+
+pub mod android;
 pub mod cargo;
+pub mod custom;
 pub mod gradle;
 pub mod maven;
 pub mod npm;
@@ -8,4 +12,6 @@ pub trait PanPackage {
     fn set_version(&mut self, version: &semver::Version) -> anyhow::Result<()>;
     fn persist(&self) -> anyhow::Result<()>;
     fn hook_after_rel(&self) -> anyhow::Result<()>;
+    /// Files changed by `hook_after_rel`, such as lockfiles
+    fn hook_outputs(&self) -> Vec<PathBuf>;
 }
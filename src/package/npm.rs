@@ -51,9 +51,18 @@ impl <F: FileSystem> PanPackage for NpmPackage<F> {
         } else if F::is_a_file(&self.path.join("pnpm-lock.yaml")) {
             CmdRunner::build("echo", &[String::from("lockfile update skipped")], &self.path)?
         } else {
-            anyhow::bail!("Cannot find any lockfile for package.json")
+            log::info!("No lockfile found next to {}, skipping lockfile update", self.path.join("package.json").display());
+            return Ok(());
         };
         runner.run()?;
         Ok(())
     }
+
+    fn hook_outputs(&self) -> Vec<PathBuf> {
+        ["package-lock.json", "yarn.lock"]
+            .into_iter()
+            .map(|lockfile| self.path.join(lockfile))
+            .filter(|lockfile| F::is_a_file(lockfile))
+            .collect()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,84 @@
+use anyhow::anyhow;
+use nom::{
+    branch::alt,
+    bytes::complete::take_while,
+    character::complete::{char, digit1, one_of},
+    combinator::{all_consuming, map_res},
+    IResult,
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated},
+};
+
+/// Evaluate an integer arithmetic expression made of `+`, `-`, `*`, `/` and parentheses.
+pub fn eval(expr: &str) -> anyhow::Result<i64> {
+    let (_, value) = all_consuming(terminated(expression, space))(expr)
+        .map_err(|e| anyhow!("Error evaluating expression '{expr}' - {e}"))?;
+    Ok(value)
+}
+
+fn space(i: &str) -> IResult<&str, &str> {
+    take_while(|c: char| c.is_whitespace())(i)
+}
+
+fn number(i: &str) -> IResult<&str, i64> {
+    map_res(preceded(space, digit1), str::parse::<i64>)(i)
+}
+
+fn factor(i: &str) -> IResult<&str, i64> {
+    alt((
+        number,
+        delimited(preceded(space, char('(')), expression, preceded(space, char(')'))),
+    ))(i)
+}
+
+fn term(i: &str) -> IResult<&str, i64> {
+    map_res(
+        pair(factor, many0(pair(preceded(space, one_of("*/")), factor))),
+        |(first, rest)| fold(first, rest),
+    )(i)
+}
+
+fn expression(i: &str) -> IResult<&str, i64> {
+    map_res(
+        pair(term, many0(pair(preceded(space, one_of("+-")), term))),
+        |(first, rest)| fold(first, rest),
+    )(i)
+}
+
+fn fold(first: i64, rest: Vec<(char, i64)>) -> anyhow::Result<i64> {
+    rest.into_iter().try_fold(first, |acc, (op, value)| {
+        match op {
+            '+' => acc.checked_add(value),
+            '-' => acc.checked_sub(value),
+            '*' => acc.checked_mul(value),
+            '/' => acc.checked_div(value),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("Cannot evaluate {acc} {op} {value}"))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn eval_operator_precedence() {
+        assert_eq!(10203, eval("1 * 10000 + 2 * 100 + 3").unwrap());
+    }
+
+    #[test]
+    fn eval_parentheses() {
+        assert_eq!(12, eval("(1 + 2) * 4").unwrap());
+    }
+
+    #[test]
+    fn eval_division_by_zero() {
+        assert!(eval("1 / 0").is_err());
+    }
+
+    #[test]
+    fn eval_unresolved_variable() {
+        assert!(eval("1 + {{major}}").is_err());
+    }
+}
@@ -1,7 +1,9 @@
+pub mod arith;
 pub mod json;
+pub mod tomlstring;
 pub mod xml;
 
-pub trait FormatCodec {
+pub trait FormatCodec: ToString {
     fn extract(&self, path: &str) -> anyhow::Result<Option<&str>>;
     fn replace(&mut self, path: &str, value: &str) -> anyhow::Result<()>;
 }
\ No newline at end of file
@@ -0,0 +1,77 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::anyhow;
+use toml_edit::{DocumentMut, Item, Value};
+
+use crate::parser::FormatCodec;
+
+pub struct TomlString {
+    doc: DocumentMut,
+}
+
+impl TomlString {
+    pub fn new(input: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            doc: input.parse::<DocumentMut>()?,
+        })
+    }
+
+    fn lookup(&self, path: &str) -> Option<&Item> {
+        path.split('.').try_fold(self.doc.as_item(), |item, key| item.get(key))
+    }
+}
+
+impl FormatCodec for TomlString {
+    fn extract(&self, path: &str) -> anyhow::Result<Option<&str>> {
+        match self.lookup(path) {
+            None => Ok(None),
+            Some(item) => item.as_str()
+                .map(Some)
+                .ok_or_else(|| anyhow!("{path} is not a string")),
+        }
+    }
+
+    fn replace(&mut self, path: &str, value: &str) -> anyhow::Result<()> {
+        if self.extract(path)?.is_none() {
+            anyhow::bail!("Could not find {path} in given toml")
+        }
+        let current = path.split('.')
+            .try_fold(self.doc.as_item_mut(), |item, key| item.get_mut(key))
+            .and_then(Item::as_value_mut)
+            .ok_or_else(|| anyhow!("Could not find {path} in given toml"))?;
+        let decor = current.decor().clone();
+        *current = Value::from(value);
+        *current.decor_mut() = decor;
+        Ok(())
+    }
+}
+
+impl Display for TomlString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toml_replace_keeps_formatting() {
+        let mut input = TomlString::new("name = \"Example\"\n\n[package]\nversion   = \"0.1.0\" # release version\n")
+            .expect("Error parsing toml");
+        assert_eq!(Some("0.1.0"), input.extract("package.version").expect("Error extracting value"));
+
+        input.replace("package.version", "0.2.0").expect("Error replacing value");
+        assert_eq!("name = \"Example\"\n\n[package]\nversion   = \"0.2.0\" # release version\n", input.to_string());
+    }
+
+    #[test]
+    fn test_toml_missing_and_non_string_keys() {
+        let input = TomlString::new("[package]\nversion = 2\n").expect("Error parsing toml");
+        assert_eq!(None, input.extract("package.name").expect("Error extracting missing key"));
+        assert_eq!(None, input.extract("workspace.package.version").expect("Error extracting missing table"));
+        let err = input.extract("package.version").expect_err("Number extracted as a string");
+        assert_eq!("package.version is not a string", err.to_string());
+    }
+}
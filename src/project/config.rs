@@ -1,96 +1,922 @@
 use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{anyhow, Context};
-use serde::Deserialize;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ObjectValidation, Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
+use crate::conf::extends;
+use crate::conf::extends::{Origins, ValueOrigin};
+use crate::conf::overrides;
+use crate::package::android::AndroidPackage;
+use crate::project::diagnostics;
+use crate::project::discovery;
+use crate::project::discovery::DiscoveryReport;
+use crate::project::hooks::{HookContext, HookStage};
 use crate::project::module::PanModule;
-use crate::system::FileSystem;
+use crate::system::{EnvVars, FileSystem};
 
-#[derive(Deserialize, Debug)]
+/// Release configuration of a project
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PanProjectConfig<F> {
+    /// Configuration files this one inherits from, relative to it. Later files override earlier ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<PathBuf>,
+    /// Version control system used to commit and tag releases
     #[serde(default = "default_vcs_config")]
     vcs: VcsConfig,
+    /// Modules released together, by name. Auto-discovered when empty
+    #[serde(default)]
     modules: HashMap<String, ProjectModule>,
+    /// Commands run during the release of the whole project, before the ones of the modules
+    #[serde(default)]
+    hooks: ProjectHooks,
+    #[serde(skip_deserializing, skip_serializing)]
+    source: Option<ConfigSource>,
+    #[serde(skip_deserializing, skip_serializing)]
+    discovery: Option<DiscoveryReport>,
+    #[serde(skip_deserializing, skip_serializing)]
+    origins: Vec<ValueOrigin>,
     #[serde(skip_deserializing, skip_serializing)]
     filesystem: PhantomData<F>,
 }
 
-#[derive(Deserialize, Debug)]
+/// Places where the configuration can be defined, in lookup order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    PanProject,
+    CargoPackage,
+    CargoWorkspace,
+    PackageJson,
+}
+
+impl ConfigSource {
+    const ALL: [ConfigSource; 4] = [
+        ConfigSource::PanProject,
+        ConfigSource::CargoPackage,
+        ConfigSource::CargoWorkspace,
+        ConfigSource::PackageJson,
+    ];
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            ConfigSource::PanProject => ".panproject.toml",
+            ConfigSource::CargoPackage | ConfigSource::CargoWorkspace => "Cargo.toml",
+            ConfigSource::PackageJson => "package.json",
+        }
+    }
+
+    /// Extract the raw configuration from the file content, if this source defines one
+    fn extract<F>(&self, file: &Path, content: &str) -> anyhow::Result<Option<PanProjectConfig<F>>> {
+        match self {
+            ConfigSource::PanProject => toml::from_str(content)
+                .map(Some)
+                .map_err(|e| diagnostics::toml_error(file, content, e)),
+            ConfigSource::CargoPackage | ConfigSource::CargoWorkspace => {
+                let manifest: CargoManifest<F> = toml::from_str(content)
+                    .map_err(|e| diagnostics::toml_error(file, content, e))?;
+                let section = if *self == ConfigSource::CargoPackage { manifest.package } else { manifest.workspace };
+                Ok(section.and_then(|s| s.metadata).and_then(|m| m.panrelease))
+            }
+            ConfigSource::PackageJson => {
+                let manifest: PackageJsonManifest<F> = serde_json::from_str(content)
+                    .map_err(|e| diagnostics::json_error(file, e))?;
+                Ok(manifest.panrelease)
+            }
+        }
+    }
+
+    /// Whether the file of this source in `dir` holds a configuration
+    fn defined_in<F: FileSystem>(&self, dir: &Path) -> bool {
+        F::read_string(&dir.join(self.file_name()))
+            .and_then(|content| self.extract_value(&content))
+            .is_ok_and(|value| value.is_some())
+    }
+
+    /// Extract the configuration as an untyped value, to be merged with inherited files
+    fn extract_value(&self, content: &str) -> anyhow::Result<Option<serde_json::Value>> {
+        let value = match self {
+            ConfigSource::PanProject => Some(toml::from_str(content)?),
+            ConfigSource::CargoPackage => toml::from_str::<serde_json::Value>(content)?
+                .pointer_mut("/package/metadata/panrelease")
+                .map(serde_json::Value::take),
+            ConfigSource::CargoWorkspace => toml::from_str::<serde_json::Value>(content)?
+                .pointer_mut("/workspace/metadata/panrelease")
+                .map(serde_json::Value::take),
+            ConfigSource::PackageJson => serde_json::from_str::<serde_json::Value>(content)?
+                .get_mut("panrelease")
+                .map(serde_json::Value::take),
+        };
+        Ok(value)
+    }
+}
+
+/// Subset of a Cargo.toml holding the embedded configuration, deserialized from the whole file to keep error positions
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct CargoManifest<F> {
+    package: Option<CargoMetadataHolder<F>>,
+    workspace: Option<CargoMetadataHolder<F>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct CargoMetadataHolder<F> {
+    metadata: Option<CargoMetadata<F>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct CargoMetadata<F> {
+    panrelease: Option<PanProjectConfig<F>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct PackageJsonManifest<F> {
+    panrelease: Option<PanProjectConfig<F>>,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::PanProject => write!(f, ".panproject.toml"),
+            ConfigSource::CargoPackage => write!(f, "[package.metadata.panrelease] in Cargo.toml"),
+            ConfigSource::CargoWorkspace => write!(f, "[workspace.metadata.panrelease] in Cargo.toml"),
+            ConfigSource::PackageJson => write!(f, "\"panrelease\" key in package.json"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(tag = "software")]
 pub enum VcsConfig {
     Git(GitConfig),
+    Mercurial(MercurialConfig),
+    /// Bump versions only, without checking the repository status, committing or tagging
+    None,
+}
+
+impl VcsConfig {
+    /// Name of the tag marking the release of `version`
+    pub fn tag_name(&self, version: &semver::Version) -> String {
+        match self {
+            VcsConfig::Git(git_conf) => git_conf.tag_name(version),
+            VcsConfig::Mercurial(hg_conf) => hg_conf.tag_name(version),
+            VcsConfig::None => version.to_string(),
+        }
+    }
+
+    /// Message of the release commit
+    pub fn commit_message(&self, ctx: &HookContext) -> String {
+        match self {
+            VcsConfig::Git(git_conf) => git_conf.commit_message(ctx),
+            VcsConfig::Mercurial(hg_conf) => ctx.render(&hg_conf.commit_template),
+            VcsConfig::None => ctx.version.to_string(),
+        }
+    }
+
+    /// Patterns of files allowed to have uncommitted changes when releasing
+    pub fn allow_dirty(&self) -> &[String] {
+        match self {
+            VcsConfig::Git(git_conf) => &git_conf.allow_dirty,
+            VcsConfig::Mercurial(hg_conf) => &hg_conf.allow_dirty,
+            VcsConfig::None => &[],
+        }
+    }
+
+    /// Message of the release tag, `None` for lightweight tags
+    pub fn tag_message(&self, ctx: &HookContext) -> Option<String> {
+        match self {
+            VcsConfig::Git(git_conf) => git_conf.annotated_tags().then(|| git_conf.tag_message(ctx)),
+            VcsConfig::Mercurial(_) | VcsConfig::None => None,
+        }
+    }
 }
 
 fn default_vcs_config() -> VcsConfig {
     VcsConfig::Git(GitConfig::default())
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// A `vcs` table not naming the software, e.g. partially inherited or created by an environment override, configures git
+fn default_vcs_software(value: &mut serde_json::Value) {
+    if let Some(vcs) = value.get_mut("vcs").and_then(serde_json::Value::as_object_mut) {
+        vcs.entry("software").or_insert_with(|| serde_json::Value::String(String::from("Git")));
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct GitConfig {
+    /// Implementation used to operate on the repository
+    #[serde(default)]
+    pub backend: GitBackend,
+    /// Sign release commits and tags
     #[serde(default)]
     pub force_sign: bool,
+    /// Sign release commits, defaults to `force_sign`
+    #[serde(default)]
+    pub sign_commits: Option<bool>,
+    /// Sign release tags, defaults to `force_sign`
+    #[serde(default)]
+    pub sign_tags: Option<bool>,
+    /// Key used to sign, instead of `user.signingkey` from the git configuration
+    #[serde(default)]
+    pub signing_key: Option<String>,
+    /// Signature format, instead of `gpg.format` from the git configuration
+    #[serde(default)]
+    pub signing_format: Option<SigningFormat>,
+    /// Author and committer of the release, instead of `user.name` and `user.email` from the git configuration
+    #[serde(default)]
+    pub identity: Option<Identity>,
+    /// Glob patterns of files allowed to have uncommitted changes when releasing, e.g. `docs/**`
+    #[serde(default)]
+    pub allow_dirty: Vec<String>,
+    /// Name of the release tag, `{{version}}` is replaced with the released version
     #[serde(default = "default_tag_template")]
     pub tag_template: String,
+    /// Message of the release commit, expanding the hook variables such as `{{version}}`, `{{tag}}` or `{{modules}}`
+    #[serde(default = "default_commit_template")]
+    pub commit_template: String,
+    /// Message of annotated tags, expanding the same variables as `commit_template`
+    #[serde(default = "default_tag_message_template")]
+    pub tag_message_template: String,
+    /// Create annotated tags, signed tags are always annotated
+    #[serde(default)]
+    pub annotated: bool,
+    /// Trailers appended to the commit message, e.g. `Release-Modules = "{{modules}}"`
+    #[serde(default)]
+    pub trailers: BTreeMap<String, String>,
 }
 
 impl Default for GitConfig {
     fn default() -> Self {
         Self {
+            backend: GitBackend::default(),
             force_sign: false,
+            sign_commits: None,
+            sign_tags: None,
+            signing_key: None,
+            signing_format: None,
+            identity: None,
+            allow_dirty: Vec::new(),
             tag_template: default_tag_template(),
+            commit_template: default_commit_template(),
+            tag_message_template: default_tag_message_template(),
+            annotated: false,
+            trailers: BTreeMap::new(),
+        }
+    }
+}
+
+impl GitConfig {
+    pub fn tag_name(&self, version: &semver::Version) -> String {
+        self.tag_template.replace("{{version}}", &version.to_string())
+    }
+
+    pub fn commit_message(&self, ctx: &HookContext) -> String {
+        let mut message = ctx.render(&self.commit_template);
+        if !self.trailers.is_empty() {
+            message.push('\n');
+            for (key, value) in self.trailers.iter() {
+                message.push_str(&format!("\n{key}: {}", ctx.render(value)));
+            }
         }
+        message
+    }
+
+    pub fn tag_message(&self, ctx: &HookContext) -> String {
+        ctx.render(&self.tag_message_template)
+    }
+
+    pub fn annotated_tags(&self) -> bool {
+        self.annotated || self.signs_tags()
+    }
+
+    pub fn signs_commits(&self) -> bool {
+        self.sign_commits.unwrap_or(self.force_sign)
+    }
+
+    pub fn signs_tags(&self) -> bool {
+        self.sign_tags.unwrap_or(self.force_sign)
+    }
+}
+
+/// Name and email recorded in release commits and tags
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl std::fmt::Display for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
     }
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    Openpgp,
+    Ssh,
+    X509,
+}
+
+impl SigningFormat {
+    /// Value of the `gpg.format` git option
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigningFormat::Openpgp => "openpgp",
+            SigningFormat::Ssh => "ssh",
+            SigningFormat::X509 => "x509",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    /// Run the `git` executable found in `PATH`
+    #[default]
+    Cli,
+    /// Use the bundled libgit2, requires the `libgit` feature
+    Libgit2,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MercurialConfig {
+    /// Name of the release tag, `{{version}}` is replaced with the released version
+    #[serde(default = "default_tag_template")]
+    pub tag_template: String,
+    /// Message of the release commit, expanding the hook variables such as `{{version}}` or `{{modules}}`
+    #[serde(default = "default_commit_template")]
+    pub commit_template: String,
+    /// Committer of the release, instead of `ui.username` from the mercurial configuration
+    #[serde(default)]
+    pub identity: Option<Identity>,
+    /// Glob patterns of files allowed to have uncommitted changes when releasing, e.g. `docs/**`
+    #[serde(default)]
+    pub allow_dirty: Vec<String>,
+}
+
+impl MercurialConfig {
+    pub fn tag_name(&self, version: &semver::Version) -> String {
+        self.tag_template.replace("{{version}}", &version.to_string())
+    }
+}
+
+fn default_commit_template() -> String {
+    String::from("{{version}}")
+}
+
+fn default_tag_message_template() -> String {
+    String::from("{{tag}}")
+}
+
 fn default_tag_template() -> String {
     String::from("{{version}}")
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "RawProjectModule")]
 pub struct ProjectModule {
     pub path: PathBuf,
-    #[serde(default = "default_main")]
     pub main: bool,
+    /// Package managers kept in sync within the module, the first one being the version source
+    #[serde(rename = "packageManagers")]
+    pub package_managers: Vec<PackageManager>,
+    pub hooks: ProjectHooks,
+    pub builtin_hooks: BuiltinHooks,
+    pub files: Vec<VersionFile>,
+}
+
+/// Module as written in the configuration, either with a single `packageManager` (and its
+/// settings inline) or with a `packageManagers` list.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "ProjectModule")]
+struct RawProjectModule {
+    /// Module directory, relative to the project root
+    path: PathBuf,
+    /// Module whose version is bumped to compute the released version
+    #[serde(default = "default_main")]
+    main: bool,
+    /// Package managers kept in sync within the module, alternative to `packageManager`
+    #[serde(default, rename = "packageManagers")]
+    package_managers: Option<Vec<PackageManagerEntry>>,
+    #[serde(default)]
+    hooks: ProjectHooks,
+    /// Whether to run the package manager hooks (e.g. `cargo check`) after the update, or a command replacing them
+    #[serde(default)]
+    builtin_hooks: BuiltinHooks,
+    /// Additional files holding a version reference
+    #[serde(default)]
+    files: Vec<VersionFile>,
     #[serde(flatten)]
-    pub package_manager: PackageManager,
+    #[schemars(with = "Option<PackageManager>")]
+    package_manager: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum PackageManagerEntry {
+    Name(String),
+    Table(#[schemars(with = "PackageManager")] serde_json::Map<String, serde_json::Value>),
+}
+
+impl JsonSchema for ProjectModule {
+    fn schema_name() -> String {
+        RawProjectModule::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = RawProjectModule::json_schema(gen).into_object();
+        // A module either inlines a single `packageManager` or lists `packageManagers`
+        if let Some(single) = schema.subschemas().one_of.take() {
+            let single = SchemaObject {
+                subschemas: Some(Box::new(SubschemaValidation { one_of: Some(single), ..Default::default() })),
+                ..Default::default()
+            };
+            let multiple = SchemaObject {
+                object: Some(Box::new(ObjectValidation { required: ["packageManagers".to_string()].into(), ..Default::default() })),
+                ..Default::default()
+            };
+            schema.subschemas().one_of = Some(vec![single.into(), multiple.into()]);
+        }
+        schema.into()
+    }
+}
+
+const MODULE_FIELDS: [&str; 7] = ["path", "main", "packageManager", "packageManagers", "hooks", "builtin_hooks", "files"];
+
+impl TryFrom<RawProjectModule> for ProjectModule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawProjectModule) -> Result<Self, Self::Error> {
+        let package_managers = match raw.package_managers {
+            Some(_) if raw.package_manager.contains_key("packageManager") => {
+                anyhow::bail!("Only one of packageManager and packageManagers can be defined")
+            }
+            Some(entries) if entries.is_empty() => anyhow::bail!("packageManagers must not be empty"),
+            Some(entries) => {
+                PackageManager::check_fields(&raw.package_manager, &MODULE_FIELDS)?;
+                entries
+                    .into_iter()
+                    .map(|entry| match entry {
+                        PackageManagerEntry::Name(name) => {
+                            let mut table = serde_json::Map::new();
+                            table.insert(String::from("packageManager"), serde_json::Value::String(name));
+                            table
+                        }
+                        PackageManagerEntry::Table(table) => table,
+                    })
+                    .map(|table| {
+                        PackageManager::check_fields(&table, &["packageManager"])?;
+                        Ok(PackageManager::deserialize(serde_json::Value::Object(table))?)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            }
+            None => {
+                PackageManager::check_fields(&raw.package_manager, &MODULE_FIELDS)?;
+                vec![PackageManager::deserialize(serde_json::Value::Object(raw.package_manager))?]
+            }
+        };
+
+        Ok(Self {
+            path: raw.path,
+            main: raw.main,
+            package_managers,
+            hooks: raw.hooks,
+            builtin_hooks: raw.builtin_hooks,
+            files: raw.files,
+        })
+    }
+}
+
+/// Additional file holding a version reference, updated through a regex search and replace
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VersionFile {
+    /// File path, relative to the module path
+    pub path: PathBuf,
+    /// Regex matching the version reference, `{{current_version}}` and `{{new_version}}` are replaced with the escaped versions
+    #[serde(default = "default_file_search")]
+    pub search: String,
+    /// Replacement of the matched text, may reference the regex capture groups
+    #[serde(default = "default_file_replace")]
+    pub replace: String,
+    /// Do not fail when the file or the pattern is missing
     #[serde(default)]
-    pub hooks: ProjectHooks,
+    pub optional: bool,
+}
+
+fn default_file_search() -> String {
+    String::from("{{current_version}}")
 }
 
-#[derive(Deserialize, Default, Debug, Clone)]
+fn default_file_replace() -> String {
+    String::from("{{new_version}}")
+}
+
+/// Commands run during the release of a module.
+/// Arguments may reference `{{version}}`, `{{previous_version}}`, `{{major}}`, `{{minor}}`, `{{patch}}`,
+/// `{{module}}` and `{{tag}}`, also exported to the commands as `PANRELEASE_VERSION`, `PANRELEASE_TAG`, ...
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
 pub struct ProjectHooks {
-    pub after_rel: BTreeMap<String, Vec<String>>,
+    /// Commands run before any file is updated. A failure aborts the release
+    pub before_rel: HookList,
+    /// Commands run after the module version is updated
+    pub after_rel: HookList,
+    /// Commands run after the changelog is updated
+    pub after_changelog: HookList,
+    /// Commands run before the release changes are staged and committed
+    pub before_commit: HookList,
+    /// Commands run after the release commit is created
+    pub after_commit: HookList,
+    /// Commands run after the release tag is created
+    pub after_tag: HookList,
+    /// Commands run when the release fails at any stage
+    pub on_failure: HookList,
+}
+
+impl ProjectHooks {
+    pub fn stage(&self, stage: HookStage) -> &HookList {
+        match stage {
+            HookStage::BeforeRel => &self.before_rel,
+            HookStage::AfterRel => &self.after_rel,
+            HookStage::AfterChangelog => &self.after_changelog,
+            HookStage::BeforeCommit => &self.before_commit,
+            HookStage::AfterCommit => &self.after_commit,
+            HookStage::AfterTag => &self.after_tag,
+            HookStage::OnFailure => &self.on_failure,
+        }
+    }
+
+    /// Patterns of the files changed by the hooks of every stage
+    pub fn outputs(&self) -> Vec<String> {
+        HookStage::ALL.iter()
+            .flat_map(|stage| self.stage(*stage).entries())
+            .flat_map(|(_, hook)| hook.options().stage)
+            .collect()
+    }
+
+    /// Every misconfigured hook, reported as `stage.name`
+    pub fn validate(&self) -> Vec<anyhow::Error> {
+        HookStage::ALL.iter()
+            .flat_map(|stage| self.stage(*stage)
+                .entries()
+                .into_iter()
+                .filter_map(move |(name, hook)| hook.options().validate().err().map(|e| anyhow!("{stage}.{name}: {e}"))))
+            .collect()
+    }
+}
+
+/// Commands of a hook stage, either a table run in name order or an array run in the listed order
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum HookList {
+    Named(BTreeMap<String, HookCommand>),
+    Ordered(Vec<HookCommand>),
+}
+
+impl Default for HookList {
+    fn default() -> Self {
+        HookList::Named(BTreeMap::new())
+    }
+}
+
+impl HookList {
+    /// Commands in execution order with their display name, unnamed array items being numbered from 1
+    pub fn entries(&self) -> Vec<(String, &HookCommand)> {
+        match self {
+            HookList::Named(hooks) => hooks.iter()
+                .map(|(name, hook)| (name.clone(), hook))
+                .collect(),
+            HookList::Ordered(hooks) => hooks.iter()
+                .enumerate()
+                .map(|(idx, hook)| match hook {
+                    HookCommand::Options(HookOptions { name: Some(name), .. }) => (name.clone(), hook),
+                    _ => (format!("#{}", idx + 1), hook),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HookList {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HookListVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for HookListVisitor {
+            type Value = HookList;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a table of named hooks or an array of hooks")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(serde::de::value::SeqAccessDeserializer::new(seq)).map(HookList::Ordered)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(serde::de::value::MapAccessDeserializer::new(map)).map(HookList::Named)
+            }
+        }
+
+        deserializer.deserialize_any(HookListVisitor)
+    }
+}
+
+/// Hook command: a shell script, the bare command line or a table with execution options
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum HookCommand {
+    Shell(String),
+    Args(Vec<String>),
+    Options(HookOptions),
+}
+
+impl HookCommand {
+    pub fn options(&self) -> HookOptions {
+        match self {
+            HookCommand::Shell(script) => HookOptions {
+                run: Some(script.clone()),
+                ..Default::default()
+            },
+            HookCommand::Args(cmd) => HookOptions {
+                cmd: cmd.clone(),
+                ..Default::default()
+            },
+            HookCommand::Options(options) => options.clone(),
+        }
+    }
+}
+
+/// Package manager hooks run after the version update: all of them, none or a replacement command
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum BuiltinHooks {
+    Enabled(bool),
+    Override(HookCommand),
+}
+
+impl Default for BuiltinHooks {
+    fn default() -> Self {
+        BuiltinHooks::Enabled(true)
+    }
+}
+
+impl<'de> Deserialize<'de> for BuiltinHooks {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BuiltinHooksVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BuiltinHooksVisitor {
+            type Value = BuiltinHooks;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a boolean or a hook command")
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, enabled: bool) -> Result<Self::Value, E> {
+                Ok(BuiltinHooks::Enabled(enabled))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                HookCommand::deserialize(serde::de::value::SeqAccessDeserializer::new(seq)).map(BuiltinHooks::Override)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                HookCommand::deserialize(serde::de::value::MapAccessDeserializer::new(map)).map(BuiltinHooks::Override)
+            }
+        }
+
+        deserializer.deserialize_any(BuiltinHooksVisitor)
+    }
+}
+
+/// Dispatch on the value type instead of using `untagged`, to keep the errors of the options table
+impl<'de> Deserialize<'de> for HookCommand {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HookCommandVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for HookCommandVisitor {
+            type Value = HookCommand;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a shell script, a command array or a table with a `cmd` or `run` key")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, script: &str) -> Result<Self::Value, E> {
+                Ok(HookCommand::Shell(script.to_string()))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(serde::de::value::SeqAccessDeserializer::new(seq)).map(HookCommand::Args)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(serde::de::value::MapAccessDeserializer::new(map)).map(HookCommand::Options)
+            }
+        }
+
+        deserializer.deserialize_any(HookCommandVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HookOptions {
+    /// Name displayed when running the hook, for hooks listed in an array
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Command and its arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cmd: Vec<String>,
+    /// Shell script run with `sh -c`, alternative to `cmd`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    /// Working directory, relative to the module (or project) directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Additional environment variables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Maximum duration such as `30s`, `5m` or `1h` (at most `168h`), the command and the processes it started are killed when exceeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<HookTimeout>,
+    /// Keep releasing when the command fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Glob patterns of the files changed by the command, relative to the module (or project) directory,
+    /// committed with the release. Any other file changed during the release is an error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stage: Vec<String>,
+}
+
+impl HookOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match (self.cmd.is_empty(), &self.run) {
+            (true, None) => anyhow::bail!("either cmd or run must be defined"),
+            (false, Some(_)) => anyhow::bail!("only one of cmd and run can be defined"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct HookTimeout(pub Duration);
+
+/// Longest hook timeout, a week
+const MAX_TIMEOUT: Duration = Duration::from_secs(7 * 24 * 3600);
+
+impl TryFrom<String> for HookTimeout {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let (amount, unit) = value.split_at(split);
+        let amount = amount.parse::<u64>()
+            .map_err(|_| anyhow!("invalid timeout '{value}', expected a number followed by ms, s, m or h"))?;
+        let too_large = || anyhow!("timeout '{value}' is too large, expected at most {}", String::from(HookTimeout(MAX_TIMEOUT)));
+        let duration = match unit.trim() {
+            "ms" => Duration::from_millis(amount),
+            "s" | "" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount.checked_mul(60).ok_or_else(too_large)?),
+            "h" => Duration::from_secs(amount.checked_mul(3600).ok_or_else(too_large)?),
+            other => anyhow::bail!("invalid timeout unit '{other}', expected ms, s, m or h"),
+        };
+        if duration > MAX_TIMEOUT {
+            return Err(too_large());
+        }
+        Ok(Self(duration))
+    }
+}
+
+impl From<HookTimeout> for String {
+    fn from(timeout: HookTimeout) -> Self {
+        let millis = timeout.0.as_millis();
+        match millis {
+            m if m % 3_600_000 == 0 => format!("{}h", m / 3_600_000),
+            m if m % 60_000 == 0 => format!("{}m", m / 60_000),
+            m if m % 1000 == 0 => format!("{}s", m / 1000),
+            m => format!("{m}ms"),
+        }
+    }
 }
 
 fn default_main() -> bool {
     false
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(tag = "packageManager")]
 pub enum PackageManager {
     Cargo,
     Npm,
     Maven,
     Gradle,
+    Android(AndroidConfig),
+    Custom(CustomConfig),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AndroidConfig {
+    /// File holding `versionName` and `versionCode`, relative to the module path
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub version_code: VersionCodeStrategy,
+}
+
+/// Version stored at a known key of a structured file
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CustomConfig {
+    /// Manifest file, relative to the module path
+    pub file: PathBuf,
+    pub format: FileFormat,
+    /// Key holding the version, `.` separated for json and toml, `/` separated for xml.
+    /// Named `version_path` as `path` is the directory of the module
+    pub version_path: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Json,
+    Xml,
+    Toml,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionCodeStrategy {
+    /// Increment the current `versionCode` by one
+    #[default]
+    Increment,
+    /// Evaluate an arithmetic expression on `{{major}}`, `{{minor}}` and `{{patch}}`
+    Formula(String),
 }
 
 impl PackageManager {
-    pub fn detect<F: FileSystem>(path: &Path) -> Option<Self> {
-        if F::is_a_file(&path.join("Cargo.toml")) {
-            Some(Self::Cargo)
-        } else if F::is_a_file(&path.join("pom.xml")) {
-            Some(Self::Maven)
-        } else if F::is_a_file(&path.join("package.json")) {
-            Some(Self::Npm)
-        } else if F::is_a_file(&path.join("gradle.properties")) {
-            Some(Self::Gradle)
-        } else {
-            None
+    /// Settings accepted inline by each package manager
+    fn fields_of(name: &str) -> &'static [&'static str] {
+        match name {
+            "Android" => &["file", "version_code"],
+            "Custom" => &["file", "format", "version_path"],
+            _ => &[],
+        }
+    }
+
+    /// Reject keys that are neither accepted by the package manager nor part of the enclosing table
+    fn check_fields(table: &serde_json::Map<String, serde_json::Value>, enclosing: &[&str]) -> anyhow::Result<()> {
+        let name = table.get("packageManager").and_then(|v| v.as_str()).unwrap_or_default();
+        let expected = enclosing.iter()
+            .chain(Self::fields_of(name))
+            .collect::<Vec<_>>();
+        if let Some(unknown) = table.keys().find(|key| !expected.contains(&&key.as_str())) {
+            let expected = expected.iter().map(|f| format!("`{f}`")).collect::<Vec<_>>();
+            anyhow::bail!("unknown field `{unknown}`, expected one of {}", expected.join(", "));
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Cargo => "Cargo",
+            PackageManager::Npm => "Npm",
+            PackageManager::Maven => "Maven",
+            PackageManager::Gradle => "Gradle",
+            PackageManager::Android(_) => "Android",
+            PackageManager::Custom(_) => "Custom",
         }
     }
+
+    /// Every package manager whose manifest is found in the given directory
+    pub fn detect<F: FileSystem>(path: &Path) -> Vec<Self> {
+        [
+            ("Cargo.toml", Self::Cargo),
+            ("pom.xml", Self::Maven),
+            ("package.json", Self::Npm),
+            ("gradle.properties", Self::Gradle),
+        ]
+            .into_iter()
+            .filter(|(manifest, _)| F::is_a_file(&path.join(manifest)))
+            .map(|(_, package_manager)| package_manager)
+            .collect()
+    }
 }
 
 impl<P> Default for PanProjectConfig<P> {
@@ -98,38 +924,141 @@ impl<P> Default for PanProjectConfig<P> {
         Self {
             vcs: default_vcs_config(),
             modules: Default::default(),
+            hooks: Default::default(),
+            extends: Vec::new(),
+            source: None,
+            discovery: None,
+            origins: Vec::new(),
             filesystem: PhantomData,
         }
     }
 }
 
 impl<F: FileSystem + 'static> PanProjectConfig<F> {
-    pub fn load(path: &Path) -> anyhow::Result<PanProjectConfig<F>> {
-        let conf_file_path = path.join(".panproject.toml");
-        if !F::is_a_file(&conf_file_path) {
-            return Ok(Default::default());
-        }
-        let conf_str = F::read_string(&conf_file_path)
-            .with_context(|| format!("Failed to read .panproject.toml from {:?}", path))?;
-        let mut conf: PanProjectConfig<F> = toml::from_str(&conf_str)?;
-
-        conf.modules
-            .iter_mut()
-            .map(|(mod_name, conf)| {
-                conf.path = path.join(&conf.path);
-                Self::validate_module(mod_name, conf)
-            })
-            .collect::<Result<Vec<()>, _>>()?;
+    /// Load the configuration from the first source defining it, see [`ConfigSource`] for the lookup order.
+    /// Defining the configuration in more than one source is an error.
+    /// Files listed in `extends` are merged below it and `PANRELEASE_*` environment variables override the result.
+    pub fn load(path: &Path) -> anyhow::Result<PanProjectConfig<F>>
+        where F: EnvVars
+    {
+        let mut found = Vec::new();
+        for source in ConfigSource::ALL {
+            let conf_file_path = path.join(source.file_name());
+            if !F::is_a_file(&conf_file_path) {
+                continue;
+            }
+            let conf_str = F::read_string(&conf_file_path)
+                .with_context(|| format!("Failed to read {} from {:?}", source.file_name(), path))?;
+            // a file inheriting from others may leave required values to them, checked once merged
+            let value = match source.extract_value(&conf_str) {
+                Ok(Some(value)) if extends::declares_extends(&value) => {
+                    diagnostics::check_keys(&conf_file_path, &conf_str, &value)
+                        .with_context(|| format!("Error parsing configuration from {source}"))?;
+                    Some(value)
+                }
+                _ => source.extract::<F>(&conf_file_path, &conf_str)
+                    .with_context(|| format!("Error parsing configuration from {source}"))?
+                    .and(source.extract_value(&conf_str)?),
+            };
+            if let Some(value) = value {
+                found.push((source, conf_file_path, value));
+            }
+        }
+
+        if found.len() > 1 {
+            let sources = found.iter().map(|(source, _, _)| source.to_string()).collect::<Vec<_>>();
+            anyhow::bail!("panrelease configuration defined in multiple places: {}. Keep only one of them", sources.join(", "));
+        }
+        let mut origins = Origins::new();
+        let (source, mut value) = match found.pop() {
+            Some((source, file, value)) => {
+                let merged = extends::resolve::<F>(value, &file, source.file_name(), path, &mut origins)
+                    .with_context(|| format!("Error resolving configuration inherited by {source}"))?;
+                (Some(source), merged)
+            }
+            None => (None, serde_json::Value::Object(Default::default())),
+        };
+        let vars = F::prefixed(overrides::ENV_PREFIX)?;
+        for (key, var) in overrides::apply_env_overrides(&mut value, &vars)? {
+            origins.insert(key, var);
+        }
+        default_vcs_software(&mut value);
+        let mut conf: PanProjectConfig<F> = serde_json::from_value(value)
+            .context("Invalid configuration after merging inherited files and environment overrides")?;
+        conf.origins = extends::explain(&serde_json::to_value(&conf)?, &origins);
+        conf.source = source;
+        let source = source.map(|s| s.to_string()).unwrap_or_else(|| String::from("environment overrides"));
+
+        let mut module_names = conf.modules.keys().cloned().collect::<Vec<_>>();
+        module_names.sort();
+        let mut problems = conf.hooks.validate()
+            .into_iter()
+            .map(|e| anyhow!("hooks.{e}"))
+            .collect::<Vec<_>>();
+        for mod_name in module_names {
+            let module_conf = conf.modules.get_mut(&mod_name).expect("Module not found");
+            module_conf.path = path.join(&module_conf.path);
+            problems.extend(Self::validate_module(&mod_name, module_conf));
+        }
+        if !problems.is_empty() {
+            let problems = problems.iter().map(|p| format!("  - {p}")).collect::<Vec<_>>();
+            anyhow::bail!("Invalid configuration in {source}:\n{}", problems.join("\n"));
+        }
 
         Ok(conf)
     }
 
+    /// Nearest directory from `path` upwards holding a configuration in any of the sources
+    pub fn find_config_dir(path: &Path) -> Option<&Path> {
+        path.ancestors()
+            .find(|dir| ConfigSource::ALL.iter().any(|source| source.defined_in::<F>(dir)))
+    }
+
     pub fn vcs(&self) -> &VcsConfig {
         &self.vcs
     }
 
-    fn validate_module(mod_name: &str, module_conf: &ProjectModule) -> anyhow::Result<()> {
-        match module_conf.package_manager {
+    pub fn hooks(&self) -> &ProjectHooks {
+        &self.hooks
+    }
+
+    pub fn source(&self) -> Option<ConfigSource> {
+        self.source
+    }
+
+    /// Register every versioned manifest found below `path` as a module, the one in `path` being the main one.
+    pub fn discover_modules(&mut self, path: &Path) -> anyhow::Result<()> {
+        let (modules, report) = discovery::discover::<F>(path)?;
+        self.modules = modules;
+        self.discovery = Some(report);
+        Ok(())
+    }
+
+    pub fn discovery(&self) -> Option<&DiscoveryReport> {
+        self.discovery.as_ref()
+    }
+
+    /// Every value of the loaded configuration with the file or variable defining it
+    pub fn origins(&self) -> &[ValueOrigin] {
+        &self.origins
+    }
+
+    /// Every problem found in the module configuration
+    fn validate_module(mod_name: &str, module_conf: &ProjectModule) -> Vec<anyhow::Error> {
+        let builtin_hooks = match &module_conf.builtin_hooks {
+            BuiltinHooks::Override(hook) => hook.options().validate().err(),
+            BuiltinHooks::Enabled(_) => None,
+        };
+        module_conf.package_managers
+            .iter()
+            .filter_map(|package_manager| Self::validate_package_manager(mod_name, module_conf, package_manager).err())
+            .chain(builtin_hooks.map(|e| anyhow!("module {mod_name} builtin_hooks: {e}")))
+            .chain(module_conf.hooks.validate().into_iter().map(|e| anyhow!("module {mod_name} hooks.{e}")))
+            .collect()
+    }
+
+    fn validate_package_manager(mod_name: &str, module_conf: &ProjectModule, package_manager: &PackageManager) -> anyhow::Result<()> {
+        match package_manager {
             PackageManager::Cargo => {
                 let cargo_toml_path = module_conf.path.join("Cargo.toml");
                 if !F::is_a_file(&cargo_toml_path) {
@@ -166,6 +1095,23 @@ impl<F: FileSystem + 'static> PanProjectConfig<F> {
                     ));
                 }
             }
+            PackageManager::Custom(custom_conf) => {
+                let manifest_path = module_conf.path.join(&custom_conf.file);
+                if !F::is_a_file(&manifest_path) {
+                    return Err(anyhow!(
+                        "Error during {mod_name} module validation. {:?} is not a valid file",
+                        manifest_path
+                    ));
+                }
+            }
+            PackageManager::Android(android_conf) => {
+                if AndroidPackage::<F>::locate(&module_conf.path, android_conf).is_none() {
+                    return Err(anyhow!(
+                        "Error during {mod_name} module validation. Could not find a gradle file defining versionName in {:?}",
+                        module_conf.path
+                    ));
+                }
+            }
         }
         Ok(())
     }
@@ -197,10 +1143,204 @@ impl<F: FileSystem + 'static> PanProjectConfig<F> {
         }
     }
 
+    /// Configured modules sorted by name, for the release order and the `{{modules}}` list to be reproducible
     pub fn modules(&self) -> anyhow::Result<Vec<PanModule<F>>> {
-        self.modules
-            .iter()
-            .map(|(name, conf)| PanModule::new(String::from(name), conf.clone()))
+        let mut names = self.modules.keys().collect::<Vec<_>>();
+        names.sort();
+        names.into_iter()
+            .map(|name| PanModule::new(name.clone(), self.modules[name].clone()))
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::system::NativeSystem;
+    use crate::test_utils::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn default_vcs_software_to_git() {
+        let mut value = json!({ "vcs": { "force_sign": true } });
+        default_vcs_software(&mut value);
+        assert_eq!(json!({ "vcs": { "software": "Git", "force_sign": true } }), value);
+
+        let mut value = json!({ "modules": {} });
+        default_vcs_software(&mut value);
+        assert_eq!(json!({ "modules": {} }), value);
+    }
+
+    #[test]
+    fn extend_partial_vcs_table() {
+        let dir = TempDir::new("config-extends");
+        dir.write("shared/base.toml", "[vcs]\ntag_template = \"v{{version}}\"\n");
+        dir.write("app/package.json", "{\"name\": \"web\", \"version\": \"0.1.0\"}");
+        dir.write("app/.panproject.toml", "extends = [\"../shared/base.toml\"]\n\n[vcs]\nforce_sign = true\n");
+
+        let conf = PanProjectConfig::<NativeSystem>::load(&dir.path().join("app")).expect("Error loading configuration");
+        let VcsConfig::Git(git_conf) = conf.vcs() else {
+            panic!("Expected git configuration");
+        };
+        assert_eq!("v{{version}}", git_conf.tag_template);
+        assert!(git_conf.force_sign);
+    }
+
+    #[test]
+    fn require_fields_once_merged() {
+        let dir = TempDir::new("config-extends");
+        dir.write("shared/base.toml", "[vcs]\ntag_template = \"v{{version}}\"\n");
+        dir.write(".panproject.toml", "extends = [\"shared/base.toml\"]\n\n[modules.web]\npath = \".\"\n");
+
+        let Err(err) = PanProjectConfig::<NativeSystem>::load(dir.path()) else {
+            panic!("Incomplete module loaded");
+        };
+        assert!(format!("{err:#}").contains("Invalid configuration after merging"), "unexpected error {err:#}");
+    }
+
+    #[test]
+    fn report_unknown_key_in_base() {
+        let dir = TempDir::new("config-extends");
+        dir.write("shared/base.toml", "[vcs]\nsoftware = \"Git\"\ntag_templat = \"v{{version}}\"\n");
+        dir.write(".panproject.toml", "extends = [\"shared/base.toml\"]\n");
+
+        let Err(err) = PanProjectConfig::<NativeSystem>::load(dir.path()) else {
+            panic!("Unknown key accepted");
+        };
+        let err = format!("{err:#}");
+        assert!(err.contains("base.toml:3:1: unknown field `tag_templat` in `vcs`"), "unexpected error {err}");
+        assert!(err.contains("did you mean `tag_template`?"), "unexpected error {err}");
+    }
+
+    #[test]
+    fn parse_single_package_manager() {
+        let module: ProjectModule = toml::from_str("path = \".\"\npackageManager = \"Android\"\nfile = \"app/build.gradle.kts\"\n")
+            .expect("Error parsing module");
+        assert!(matches!(
+            module.package_managers.as_slice(),
+            [PackageManager::Android(AndroidConfig { file: Some(_), .. })]
+        ));
+    }
+
+    #[test]
+    fn parse_multiple_package_managers() {
+        let module: ProjectModule = toml::from_str(r#"
+            path = "."
+            packageManagers = ["Cargo", { packageManager = "Custom", file = "deno.json", format = "json", version_path = "version" }]
+        "#).expect("Error parsing module");
+        assert!(matches!(
+            module.package_managers.as_slice(),
+            [PackageManager::Cargo, PackageManager::Custom(_)]
+        ));
+    }
+
+    #[test]
+    fn extract_cargo_metadata_config() {
+        let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[package.metadata.panrelease.vcs]\nsoftware = \"Git\"\ntag_template = \"v{{version}}\"\n";
+        let conf = ConfigSource::CargoPackage.extract::<()>(Path::new("Cargo.toml"), manifest)
+            .expect("Error parsing manifest")
+            .expect("Configuration not found");
+        let VcsConfig::Git(git_conf) = conf.vcs else {
+            panic!("Expected git configuration");
+        };
+        assert_eq!("v{{version}}", git_conf.tag_template);
+        assert!(ConfigSource::CargoWorkspace.extract::<()>(Path::new("Cargo.toml"), manifest).expect("Error parsing manifest").is_none());
+    }
+
+    #[test]
+    fn extract_package_json_config() {
+        let manifest = r#"{"name": "demo", "version": "0.1.0", "panrelease": {"modules": {"web": {"path": ".", "packageManager": "Npm"}}}}"#;
+        let conf = ConfigSource::PackageJson.extract::<()>(Path::new("package.json"), manifest)
+            .expect("Error parsing manifest")
+            .expect("Configuration not found");
+        assert!(conf.modules.contains_key("web"));
+    }
+
+    #[test]
+    fn parse_hook_forms() {
+        let hooks: ProjectHooks = toml::from_str(r#"
+            [after_rel]
+            build = ["cargo", "build"]
+            upload = { cmd = ["./upload.sh"], cwd = "dist", env = { TARGET = "prod" }, timeout = "5m", continue_on_error = true }
+        "#).expect("Error parsing hooks");
+        let HookList::Named(after_rel) = &hooks.after_rel else {
+            panic!("Expected named hooks");
+        };
+        assert_eq!(vec![String::from("cargo"), String::from("build")], after_rel["build"].options().cmd);
+        let upload = after_rel["upload"].options();
+        assert_eq!(Some(HookTimeout(Duration::from_secs(300))), upload.timeout);
+        assert!(upload.continue_on_error);
+
+        let err = toml::from_str::<ProjectHooks>("[after_rel]\nupload = { cmd = [\"x\"], timout = \"5m\" }\n").unwrap_err();
+        assert!(err.message().contains("unknown field `timout`"));
+        assert!(toml::from_str::<ProjectHooks>("[after_rel]\nupload = { cmd = [\"x\"], timeout = \"5 days\" }\n").is_err());
+    }
+
+    #[test]
+    fn reject_overflowing_timeout() {
+        assert_eq!(HookTimeout(Duration::from_millis(100)), HookTimeout::try_from(String::from("100ms")).expect("Error parsing timeout"));
+        assert_eq!(HookTimeout(MAX_TIMEOUT), HookTimeout::try_from(String::from("168h")).expect("Error parsing timeout"));
+        for value in ["18446744073709551615s", "18446744073709551615ms", "307445734561825861h", "169h"] {
+            let err = HookTimeout::try_from(String::from(value)).expect_err("Overflowing timeout parsed");
+            assert!(err.to_string().contains("too large"), "unexpected error {err}");
+        }
+    }
+
+    #[test]
+    fn parse_ordered_hooks() {
+        let hooks: ProjectHooks = toml::from_str(r#"
+            after_rel = [
+                "cargo build && cargo test",
+                ["npm", "run", "build"],
+                { name = "upload", run = "./upload.sh", timeout = "1m" },
+                { cmd = ["true"], run = "true" },
+            ]
+        "#).expect("Error parsing hooks");
+        let names = hooks.after_rel.entries().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(vec!["#1", "#2", "upload", "#4"], names);
+        assert_eq!(Some(String::from("cargo build && cargo test")), hooks.after_rel.entries()[0].1.options().run);
+        assert_eq!(1, hooks.validate().len());
+    }
+
+    #[test]
+    fn render_commit_message_with_trailers() {
+        let git_conf: GitConfig = toml::from_str(r#"
+            commit_template = "chore(release): {{tag}}"
+            [trailers]
+            Release-Modules = "{{modules}}"
+            Signed-off-by = "Release Bot <bot@example.com>"
+        "#).expect("Error parsing git config");
+        let ctx = HookContext {
+            module: String::from("core"),
+            version: semver::Version::new(1, 2, 3),
+            previous_version: semver::Version::new(1, 2, 2),
+            tag: String::from("v1.2.3"),
+            modules: vec![(String::from("core"), semver::Version::new(1, 2, 3)), (String::from("web"), semver::Version::new(1, 2, 3))],
+        };
+        assert_eq!(
+            "chore(release): v1.2.3\n\nRelease-Modules: core@1.2.3, web@1.2.3\nSigned-off-by: Release Bot <bot@example.com>",
+            git_conf.commit_message(&ctx)
+        );
+        assert_eq!("v1.2.3", git_conf.tag_message(&ctx));
+        assert!(!git_conf.annotated_tags());
+    }
+
+    #[test]
+    fn parse_builtin_hooks() {
+        let module: ProjectModule = toml::from_str("path = \".\"\npackageManager = \"Cargo\"\nbuiltin_hooks = false\n")
+            .expect("Error parsing module");
+        assert!(matches!(module.builtin_hooks, BuiltinHooks::Enabled(false)));
+        let module: ProjectModule = toml::from_str("path = \".\"\npackageManager = \"Cargo\"\nbuiltin_hooks = [\"cargo\", \"update\", \"-w\"]\n")
+            .expect("Error parsing module");
+        assert!(matches!(module.builtin_hooks, BuiltinHooks::Override(HookCommand::Args(_))));
+    }
+
+    #[test]
+    fn reject_both_package_manager_forms() {
+        let parsed = toml::from_str::<ProjectModule>("path = \".\"\npackageManager = \"Npm\"\npackageManagers = [\"Cargo\"]\n");
+        assert!(parsed.is_err());
+    }
+}
+
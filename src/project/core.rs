@@ -1,33 +1,51 @@
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context};
 use chrono::Utc;
+use globset::{GlobBuilder, GlobSetBuilder};
 use regex::Regex;
 
 use crate::args::RelArgs;
-use crate::git::GitRepo;
+use crate::conf::extends::normalize;
 use crate::project::config::{PanProjectConfig, VcsConfig};
+use crate::project::hooks;
+use crate::project::hooks::{HookContext, HookStage};
 use crate::project::module::PanModule;
-use crate::system::FileSystem;
+use crate::system::{EnvVars, FileSystem};
+use crate::vcs;
+use crate::vcs::{Change, VcsBackend};
 
 const UNRELEASED_LINE: &str = "\n## [Unreleased]";
 
 pub struct PanProject<F> {
     path: PathBuf,
     conf: PanProjectConfig<F>,
-    repo: GitRepo,
+    repo: Box<dyn VcsBackend>,
 }
 
 impl <F: FileSystem + 'static> PanProject<F> {
-    pub fn load(path: &Path) -> anyhow::Result<Self> {
-        let project_root = GitRepo::find_git_root::<F>(path)
-            .context("Error extracting project path from repo")?;
-        let conf = PanProjectConfig::load(project_root)?;
-
-        let git_conf = match conf.vcs() {
-            VcsConfig::Git(git_conf) => git_conf,
+    pub fn load(path: &Path) -> anyhow::Result<Self>
+        where F: EnvVars
+    {
+        let (project_root, mut conf) = match vcs::find_root::<F>(path) {
+            Ok(repo_root) => (repo_root, PanProjectConfig::load(repo_root)?),
+            Err(e) => {
+                // without repository the configuration must opt out of version control, its directory being the root
+                let config_dir = PanProjectConfig::<F>::find_config_dir(path).unwrap_or(path);
+                let conf = PanProjectConfig::load(config_dir)?;
+                if !matches!(conf.vcs(), VcsConfig::None) {
+                    return Err(e).context("Error extracting project path from repo, set `software = \"None\"` in [vcs] to release without version control");
+                }
+                (config_dir, conf)
+            }
         };
-        let repo = GitRepo::open::<F>(git_conf.clone(), path)?;
+        if conf.modules()?.is_empty() {
+            conf.discover_modules(project_root)
+                .context("Error discovering project modules")?;
+        }
+
+        let repo = vcs::open::<F>(conf.vcs(), path)?;
 
         Ok(Self {
             path: project_root.to_path_buf(),
@@ -37,22 +55,138 @@ impl <F: FileSystem + 'static> PanProject<F> {
     }
 
     pub fn release(&self, rel_args: RelArgs) -> anyhow::Result<()> {
-        if !self.repo.is_staging_clean()? {
-            return Err(anyhow!("Repository status is not clean"));
+        let dirty = self.repo.status()?;
+        if !rel_args.allow_dirty {
+            self.check_clean(&dirty)?;
+        }
+        // forget files written before the release, only the ones written by the release are committed
+        F::take_written();
+        let master = self.extract_master()?;
+        let previous_version = master.extract_version()?;
+        let new_version = rel_args.level_or_version.apply(previous_version.clone());
+        let tag = self.conf.vcs().tag_name(&new_version);
+        let modules = self.extract_modules()?;
+        let released = modules.iter()
+            .map(|module| (module.name().to_string(), new_version.clone()))
+            .collect::<Vec<_>>();
+        let project_ctx = HookContext {
+            module: master.name().to_string(),
+            version: new_version.clone(),
+            previous_version,
+            tag: tag.clone(),
+            modules: released.clone(),
+        };
+        let mut modules = modules
+            .into_iter()
+            .map(|module| Ok((HookContext {
+                module: module.name().to_string(),
+                version: new_version.clone(),
+                previous_version: module.extract_version()?,
+                tag: tag.clone(),
+                modules: released.clone(),
+            }, module)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let result = self.run_release(&project_ctx, &mut modules, &dirty);
+        if result.is_err() {
+            if let Err(err) = self.run_stage(HookStage::OnFailure, &project_ctx, &modules) {
+                log::error!("Error running on_failure hooks - {err}");
+            }
         }
-        let new_version = rel_args.level_or_version.apply(self.extract_master()?.extract_version()?);
-        for mut module in self.extract_modules()? {
-            module.set_version(&new_version)?;
+        result
+    }
+
+    /// Fail listing the uncommitted changes not allowed by `allow_dirty`
+    fn check_clean(&self, dirty: &[Change]) -> anyhow::Result<()> {
+        let changes = vcs::disallowed_changes(dirty.to_vec(), self.conf.vcs().allow_dirty())?;
+        if !changes.is_empty() {
+            let changes = changes.iter().map(|change| format!("  {change}")).collect::<Vec<_>>();
+            anyhow::bail!(
+                "Repository status is not clean, commit the changes, list them in allow_dirty or pass --allow-dirty:\n{}",
+                changes.join("\n"),
+            );
+        }
+        Ok(())
+    }
+
+    fn run_release(&self, project_ctx: &HookContext, modules: &mut [(HookContext, PanModule<F>)], dirty: &[Change]) -> anyhow::Result<()> {
+        self.run_stage(HookStage::BeforeRel, project_ctx, modules)?;
+        for (ctx, module) in modules.iter_mut() {
+            module.set_version(&ctx.version)?;
             module.persist()?;
-            module.hook_after_rel()?;
+            module.hook_after_rel(ctx)?;
         }
+        self.run_project_hooks(HookStage::AfterRel, project_ctx)?;
+
+        self.update_changelog(&project_ctx.version)?;
+        self.run_stage(HookStage::AfterChangelog, project_ctx, modules)?;
 
-        self.update_changelog(&new_version)?;
-        self.repo.update_and_commit(new_version)?;
+        self.run_stage(HookStage::BeforeCommit, project_ctx, modules)?;
+        let paths = self.release_paths(modules, dirty)?;
+        self.repo.commit(&self.conf.vcs().commit_message(project_ctx), &paths)?;
+        self.run_stage(HookStage::AfterCommit, project_ctx, modules)?;
 
+        self.repo.tag(&project_ctx.tag, self.conf.vcs().tag_message(project_ctx).as_deref())?;
+        self.run_stage(HookStage::AfterTag, project_ctx, modules)
+    }
+
+    /// Paths to commit: the files written by panrelease and the outputs declared by hooks.
+    /// Any other file changed since the release started is an error, changes found before it are left out
+    fn release_paths(&self, modules: &[(HookContext, PanModule<F>)], dirty: &[Change]) -> anyhow::Result<Vec<String>> {
+        let written = F::take_written()
+            .iter()
+            .map(|file| normalize(file))
+            .collect::<BTreeSet<_>>();
+        let mut outputs = GlobSetBuilder::new();
+        let patterns = self.conf.hooks().outputs()
+            .into_iter()
+            .map(|pattern| hooks::output_pattern(&self.path, &pattern))
+            .chain(modules.iter().flat_map(|(_, module)| module.hook_outputs()));
+        for pattern in patterns {
+            outputs.add(GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid hook stage pattern '{pattern}'"))?);
+        }
+        let outputs = outputs.build()?;
+        let root = normalize(&self.path);
+
+        let mut paths = Vec::new();
+        let mut unexpected = Vec::new();
+        for change in self.repo.status()? {
+            let file = root.join(&change.path);
+            if written.contains(&file) || outputs.is_match(&file) {
+                paths.push(change.path);
+            } else if !dirty.iter().any(|before| before.path == change.path) {
+                unexpected.push(format!("  {change}"));
+            }
+        }
+        if !unexpected.is_empty() {
+            anyhow::bail!(
+                "Files changed during the release were not written by panrelease nor listed in the `stage` option of a hook:\n{}",
+                unexpected.join("\n"),
+            );
+        }
+        paths.extend(self.repo.untracked()?
+            .into_iter()
+            .filter(|path| outputs.is_match(root.join(path))));
+        Ok(paths)
+    }
+
+    /// Run the project hooks of the stage, then the ones of every module
+    fn run_stage(&self, stage: HookStage, project_ctx: &HookContext, modules: &[(HookContext, PanModule<F>)]) -> anyhow::Result<()> {
+        self.run_project_hooks(stage, project_ctx)?;
+        for (ctx, module) in modules.iter() {
+            module.run_hooks(stage, ctx)?;
+        }
         Ok(())
     }
 
+    fn run_project_hooks(&self, stage: HookStage, ctx: &HookContext) -> anyhow::Result<()> {
+        hooks::run_hooks(self.conf.hooks().stage(stage), stage, ctx, &self.path)
+            .context("Error running project hooks")
+    }
+
     fn update_changelog(&self, version: &semver::Version) -> anyhow::Result<()> {
         let changelog_path = self.path.join("CHANGELOG.md");
         if F::is_a_file(&changelog_path) {
@@ -72,25 +206,320 @@ impl <F: FileSystem + 'static> PanProject<F> {
         Ok(())
     }
 
+    pub fn show(&self) -> anyhow::Result<()> {
+        println!("Project root: {}", self.path.display());
+        match (self.conf.source(), self.conf.discovery()) {
+            (Some(source), Some(_)) => println!("Configuration: {source}, modules auto-discovered"),
+            (Some(source), None) => println!("Configuration: {source}"),
+            (None, _) => println!("Configuration: none found, modules auto-discovered"),
+        }
+
+        let modules = self.extract_modules()?;
+        let master_name = self.extract_master().ok().map(|m| m.name().to_string());
+
+        println!("Modules:");
+        for module in modules {
+            let conf = module.conf();
+            let rel_path = conf.path.strip_prefix(&self.path).unwrap_or(&conf.path);
+            let version = module.extract_version()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|e| format!("<error: {e}>"));
+            let main_marker = if master_name.as_deref() == Some(module.name()) { " (main)" } else { "" };
+            println!(
+                "  {}{main_marker}: {} manifest in ./{} at version {version}",
+                module.name(),
+                conf.package_managers.iter().map(|pm| pm.name()).collect::<Vec<_>>().join(" + "),
+                rel_path.display(),
+            );
+        }
+        if master_name.is_none() {
+            println!("No main module could be inferred, mark one with `main = true` in .panproject.toml");
+        }
+
+        if let Some(report) = self.conf.discovery() {
+            for (path, reason) in report.skipped.iter() {
+                let rel_path = path.strip_prefix(&self.path).unwrap_or(path);
+                println!("Skipped ./{}: {reason}", rel_path.display());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn show_config(&self) {
+        match self.conf.source() {
+            Some(source) => println!("# Configuration: {source}"),
+            None => println!("# Configuration: none found"),
+        }
+        let width = self.conf.origins().iter().map(|o| o.key.len() + o.value.to_string().len()).max().unwrap_or(0);
+        for value_origin in self.conf.origins() {
+            let entry = format!("{} = {}", value_origin.key, value_origin.value);
+            println!("{entry:<0$}  # {1}", width + 3, value_origin.origin);
+        }
+    }
+
     fn extract_modules(&self) -> anyhow::Result<Vec<PanModule<F>>> {
         let modules = self.conf.modules()?;
         if modules.is_empty() {
-            let detected = PanModule::detect(self.path.clone())?
-                .ok_or_else(|| anyhow!("Could not detect package"))?;
-            Ok(vec![ detected ])
+            Err(anyhow!("Could not detect package"))
         } else {
             Ok(modules)
         }
     }
 
     fn extract_master(&self) -> anyhow::Result<PanModule<F>> {
-        let maybe_master = self.conf.extract_master_mod()?;
-        if let Some(master) = maybe_master {
-            Ok(master)
-        } else {
-            let detected = PanModule::detect(self.path.clone())?
-                .ok_or_else(|| anyhow!("Could not detect package"))?;
-            Ok(detected)
+        self.conf.extract_master_mod()?
+            .ok_or_else(|| anyhow!("Could not detect package"))
+    }
+}
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use crate::args::{BumpLevel, TargetVersion};
+    use crate::system::NativeSystem;
+    use crate::test_utils::{TempDir, TempRepo};
+    use crate::vcs::Change;
+
+    use super::*;
+
+    /// Backend recording the operations requested by the release
+    #[derive(Default)]
+    struct FakeVcs {
+        /// Successive results of `status`, empty once exhausted
+        statuses: RefCell<VecDeque<Vec<Change>>>,
+        untracked: Vec<String>,
+        calls: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl FakeVcs {
+        fn with_statuses<const N: usize>(statuses: [&[&str]; N]) -> Self {
+            let statuses = statuses.iter()
+                .map(|paths| paths.iter().map(|path| modified(path)).collect())
+                .collect();
+            Self { statuses: RefCell::new(statuses), ..Self::default() }
+        }
+    }
+
+    fn modified(path: &str) -> Change {
+        Change { path: path.to_string(), status: String::from(" M") }
+    }
+
+    impl VcsBackend for FakeVcs {
+        fn status(&self) -> anyhow::Result<Vec<Change>> {
+            Ok(self.statuses.borrow_mut().pop_front().unwrap_or_default())
+        }
+
+        fn untracked(&self) -> anyhow::Result<Vec<String>> {
+            Ok(self.untracked.clone())
+        }
+
+        fn commit(&self, message: &str, paths: &[String]) -> anyhow::Result<()> {
+            self.calls.borrow_mut().push(format!("commit {message} {paths:?}"));
+            Ok(())
+        }
+
+        fn tag(&self, name: &str, message: Option<&str>) -> anyhow::Result<()> {
+            self.calls.borrow_mut().push(format!("tag {name} {message:?}"));
+            Ok(())
+        }
+
+        fn log(&self, _limit: usize) -> anyhow::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn tags(&self) -> anyhow::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn branch(&self) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn push(&self, _tags: &[String]) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    const STAGES: [&str; 7] = ["before_rel", "after_rel", "after_changelog", "before_commit", "after_commit", "after_tag", "on_failure"];
+
+    /// Npm project whose hooks append their stage, the commit count and the tags to `journal.log`,
+    /// the hooks of the `failing` stages exiting with an error
+    fn hooked_project(name: &str, failing: &[&str]) -> TempRepo {
+        let repo = TempRepo::new(name);
+        let hooks = STAGES.iter()
+            .map(|stage| match failing.contains(stage) {
+                true => format!("[hooks.{stage}]\nfail = [\"false\"]\n"),
+                false => format!("[hooks.{stage}]\njournal = [\"sh\", \"-c\", \"echo {stage} $(git rev-list --count HEAD) $(git tag) >> journal.log\"]\n"),
+            })
+            .collect::<Vec<_>>();
+        repo.write(".panproject.toml", &format!("[modules.web]\npath = \".\"\npackageManager = \"Npm\"\nmain = true\n\n{}", hooks.join("\n")));
+        repo.write("package.json", "{\n  \"name\": \"web\",\n  \"version\": \"0.1.0\"\n}\n");
+        repo.write("pnpm-lock.yaml", "");
+        repo.write(".gitignore", "journal.log\n");
+        repo.commit_all("Initial commit");
+        repo
+    }
+
+    fn release_minor(repo: &TempRepo) -> anyhow::Result<()> {
+        PanProject::<NativeSystem>::load(repo.path())?.release(minor(false))
+    }
+
+    #[test]
+    fn run_hook_stages_in_order() {
+        let repo = hooked_project("stages", &[]);
+        release_minor(&repo).expect("Error releasing project");
+
+        assert_eq!(
+            "before_rel 1\nafter_rel 1\nafter_changelog 1\nbefore_commit 1\nafter_commit 2\nafter_tag 2 0.2.0\n",
+            repo.read("journal.log"),
+        );
+    }
+
+    #[test]
+    fn abort_on_failing_before_rel() {
+        let repo = hooked_project("before-rel", &["before_rel"]);
+        release_minor(&repo).expect_err("Release not aborted by before_rel");
+
+        assert_eq!("on_failure 1\n", repo.read("journal.log"));
+        assert!(repo.read("package.json").contains("\"version\": \"0.1.0\""));
+    }
+
+    #[test]
+    fn run_on_failure_after_failing_stage() {
+        let repo = hooked_project("after-commit", &["after_commit"]);
+        release_minor(&repo).expect_err("Failing after_commit hook ignored");
+
+        assert_eq!(
+            "before_rel 1\nafter_rel 1\nafter_changelog 1\nbefore_commit 1\non_failure 2\n",
+            repo.read("journal.log"),
+        );
+        assert_eq!("", repo.git(&["tag"]));
+    }
+
+    #[test]
+    fn list_released_modules_by_name() {
+        let repo = TempRepo::new("modules");
+        let module = |name: &str, path: &str, main: bool| format!("[modules.{name}]\npath = \"{path}\"\npackageManager = \"Npm\"\nmain = {main}\n");
+        repo.write(".panproject.toml", &format!(
+            "[vcs]\nsoftware = \"Git\"\n\n[vcs.trailers]\nRelease-Modules = \"{{{{modules}}}}\"\n\n{}\n{}\n{}",
+            module("worker", "worker", false),
+            module("web", ".", false),
+            module("api", "api", true),
+        ));
+        for dir in [".", "api", "worker"] {
+            repo.write(&format!("{dir}/package.json"), "{\n  \"name\": \"module\",\n  \"version\": \"0.1.0\"\n}\n");
+            repo.write(&format!("{dir}/pnpm-lock.yaml"), "");
+        }
+        repo.commit_all("Initial commit");
+        release_minor(&repo).expect("Error releasing project");
+
+        assert_eq!("0.2.0\n\nRelease-Modules: api@0.2.0, web@0.2.0, worker@0.2.0", repo.git(&["log", "-1", "--format=%B"]).trim_end());
+    }
+
+    /// Npm project at version 0.1.0 in `dir` released through the fake backend, `extra_conf` follows the `[vcs]` section
+    fn fake_project(dir: &TempDir, extra_conf: &str, fake: FakeVcs) -> PanProject<NativeSystem> {
+        dir.write("package.json", "{\n  \"name\": \"web\",\n  \"version\": \"0.1.0\"\n}\n");
+        dir.write(".panproject.toml", &format!("[vcs]\nsoftware = \"Git\"\n{extra_conf}\n[modules.web]\npath = \".\"\npackageManager = \"Npm\"\n"));
+        PanProject {
+            conf: PanProjectConfig::load(dir.path()).expect("Error loading configuration"),
+            path: dir.path().to_path_buf(),
+            repo: Box::new(fake),
         }
     }
-}
\ No newline at end of file
+
+    fn minor(allow_dirty: bool) -> RelArgs {
+        RelArgs { level_or_version: TargetVersion::Relative(BumpLevel::Minor), allow_dirty }
+    }
+
+    #[test]
+    fn release_through_vcs_backend() {
+        let fake = FakeVcs::with_statuses([&[], &["package.json"]]);
+        let calls = fake.calls.clone();
+        let dir = TempDir::new("release");
+        let project = fake_project(&dir, "tag_template = \"v{{version}}\"\nannotated = true\n", fake);
+        project.release(minor(false)).expect("Error releasing project");
+
+        assert_eq!(vec![String::from("commit 0.2.0 [\"package.json\"]"), String::from("tag v0.2.0 Some(\"v0.2.0\")")], *calls.borrow());
+        assert!(dir.read("package.json").contains("\"version\": \"0.2.0\""));
+    }
+
+    #[test]
+    fn reject_changes_not_allowed_dirty() {
+        let dirty: &[&str] = &["docs/guide.md", "src/index.ts"];
+        let fake = FakeVcs::with_statuses([dirty, dirty, &["docs/guide.md", "package.json", "src/index.ts"]]);
+        let calls = fake.calls.clone();
+        let dir = TempDir::new("dirty");
+        let project = fake_project(&dir, "allow_dirty = [\"docs/**\"]\n", fake);
+
+        let err = project.release(minor(false)).expect_err("Dirty repository released").to_string();
+        assert!(err.contains(" M src/index.ts") && !err.contains("docs/guide.md"), "unexpected error {err}");
+        assert!(calls.borrow().is_empty());
+
+        project.release(minor(true)).expect("Error releasing with --allow-dirty");
+        assert_eq!("commit 0.2.0 [\"package.json\"]", calls.borrow()[0]);
+    }
+
+    #[test]
+    fn commit_declared_hook_outputs() {
+        let hooks = "[hooks]\nbefore_commit = [{ run = \"true\", stage = [\"dist/**\"] }]\n";
+        let fake = FakeVcs {
+            untracked: vec![String::from("dist/new.js"), String::from("notes.txt")],
+            ..FakeVcs::with_statuses([&[], &["dist/app.js", "package.json"]])
+        };
+        let calls = fake.calls.clone();
+        let dir = TempDir::new("stage");
+        let project = fake_project(&dir, hooks, fake);
+        project.release(minor(false)).expect("Error releasing project");
+
+        assert_eq!("commit 0.2.0 [\"dist/app.js\", \"package.json\", \"dist/new.js\"]", calls.borrow()[0]);
+    }
+
+    #[test]
+    fn reject_undeclared_changes() {
+        let fake = FakeVcs::with_statuses([&[], &["package.json", "src/generated.ts"]]);
+        let calls = fake.calls.clone();
+        let dir = TempDir::new("undeclared");
+        let project = fake_project(&dir, "", fake);
+
+        let err = project.release(minor(false)).expect_err("Undeclared change committed").to_string();
+        assert!(err.contains(" M src/generated.ts") && !err.contains("package.json"), "unexpected error {err}");
+        assert!(calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn release_git_repository() {
+        let repo = TempRepo::new("git");
+        repo.write("package.json", "{\n  \"name\": \"web\",\n  \"version\": \"0.1.0\"\n}\n");
+        repo.write(".panproject.toml", "[vcs]\nsoftware = \"Git\"\n\n[modules.web]\npath = \".\"\npackageManager = \"Npm\"\n");
+        repo.commit_all("init");
+
+        let project = PanProject::<NativeSystem>::load(repo.path()).expect("Error loading project");
+        project.release(minor(false)).expect("Error releasing project");
+
+        assert_eq!("M\tpackage.json\n", repo.git(&["show", "--format=", "--name-status", "HEAD"]));
+        assert_eq!("", repo.git(&["status", "--porcelain"]));
+    }
+
+    #[test]
+    fn release_without_repository() {
+        let dir = TempDir::new("novcs");
+        dir.write("web/package.json", "{\n  \"name\": \"web\",\n  \"version\": \"1.4.2\"\n}\n");
+        dir.write(".panproject.toml", r#"
+            [vcs]
+            software = "None"
+
+            [modules.web]
+            path = "web"
+            packageManager = "Npm"
+        "#);
+
+        let project = PanProject::<NativeSystem>::load(&dir.path().join("web")).expect("Error loading project");
+        assert_eq!(dir.path(), project.path);
+        project.release(RelArgs { level_or_version: TargetVersion::Relative(BumpLevel::Patch), allow_dirty: false })
+            .expect("Error releasing project");
+
+        assert!(dir.read("web/package.json").contains("\"version\": \"1.4.3\""));
+    }
+}
@@ -0,0 +1,192 @@
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::anyhow;
+use regex::Regex;
+
+use crate::conf::schema;
+
+/// Turn a toml deserialization error into a `file:line:column` diagnostic pointing at the offending key.
+pub fn toml_error(file: &Path, content: &str, err: toml::de::Error) -> anyhow::Error {
+    let message = err.message().trim_end();
+    let span = err.span()
+        .map(|span| narrow_span(content, span, message))
+        .unwrap_or(0..0);
+    anyhow!(positioned(file, content, span.start, message))
+}
+
+/// Check the keys of a configuration layer against the configuration schema, reporting every unknown key
+/// as a `file:line:column` diagnostic. Missing values are left to the merged configuration.
+pub fn check_keys(file: &Path, content: &str, layer: &serde_json::Value) -> anyhow::Result<()> {
+    let diagnostics = schema::unknown_keys(layer)?
+        .into_iter()
+        .map(|unknown| {
+            let table = if unknown.table.is_empty() { String::new() } else { format!(" in `{}`", unknown.table) };
+            let expected = unknown.expected.iter().map(|k| format!("`{k}`")).collect::<Vec<_>>();
+            let message = format!("unknown field `{}`{table}, expected one of {}", unknown.key, expected.join(", "));
+            positioned(file, content, key_offset(file, content, &unknown.key), &message)
+        })
+        .collect::<Vec<_>>();
+    if !diagnostics.is_empty() {
+        anyhow::bail!(diagnostics.join("\n\n"));
+    }
+    Ok(())
+}
+
+/// Turn a json deserialization error into a `file:line:column` diagnostic.
+pub fn json_error(file: &Path, err: serde_json::Error) -> anyhow::Error {
+    let full_message = err.to_string();
+    let message = Regex::new(r" at line \d+ column \d+$")
+        .expect("Invalid regex")
+        .replace(&full_message, "");
+
+    let mut diagnostic = format!("{}:{}:{}: {message}", file.display(), err.line(), err.column());
+    if let Some(suggestion) = suggest(&message) {
+        diagnostic.push_str(&format!("\n  = help: did you mean `{suggestion}`?"));
+    }
+    anyhow!(diagnostic)
+}
+
+fn positioned(file: &Path, content: &str, offset: usize, message: &str) -> String {
+    let (line, column) = line_col(content, offset);
+    let source_line = content.lines().nth(line - 1).unwrap_or_default();
+
+    let mut diagnostic = format!("{}:{line}:{column}: {message}\n  |\n  | {source_line}", file.display());
+    if let Some(suggestion) = suggest(message) {
+        diagnostic.push_str(&format!("\n  = help: did you mean `{suggestion}`?"));
+    }
+    diagnostic
+}
+
+/// Offset of the first definition of `key` in a toml or json file, its start when not found
+fn key_offset(file: &Path, content: &str, key: &str) -> usize {
+    let key = regex::escape(key);
+    let pattern = if file.extension().is_some_and(|ext| ext == "json") {
+        format!(r#"(?P<key>"{key}")\s*:"#)
+    } else {
+        format!(r#"(?m)(?:^|[.\[])[ \t]*(?P<key>"?{key}"?)[ \t]*[=.\]]"#)
+    };
+    Regex::new(&pattern)
+        .ok()
+        .and_then(|re| re.captures(content))
+        .and_then(|cap| cap.name("key"))
+        .map(|m| m.start())
+        .unwrap_or(0)
+}
+
+/// Unknown field errors are reported on the whole table, move the span to the unknown key when it can be found.
+fn narrow_span(content: &str, span: Range<usize>, message: &str) -> Range<usize> {
+    let Some(key) = unknown_name(message) else {
+        return span;
+    };
+    let Some(table) = content.get(span.clone()) else {
+        return span;
+    };
+    Regex::new(&format!(r#"(?m)^[ \t]*(?P<key>"?{}"?)[ \t]*="#, regex::escape(key)))
+        .ok()
+        .and_then(|re| re.captures(table))
+        .and_then(|cap| cap.name("key"))
+        .map(|m| span.start + m.start()..span.start + m.end())
+        .unwrap_or(span)
+}
+
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map(|nl| offset - nl).unwrap_or(offset + 1);
+    (line, column)
+}
+
+fn unknown_name(message: &str) -> Option<&str> {
+    Regex::new(r"unknown (?:field|variant) `(?P<name>[^`]+)`")
+        .expect("Invalid regex")
+        .captures(message)
+        .and_then(|cap| cap.name("name"))
+        .map(|m| m.as_str())
+}
+
+/// Closest expected name for an unknown field or variant error, if any is similar enough.
+fn suggest(message: &str) -> Option<String> {
+    let unknown = unknown_name(message)?.to_lowercase();
+    let (_, expected) = message.split_once("expected")?;
+    Regex::new(r"`(?P<name>[^`]+)`")
+        .expect("Invalid regex")
+        .captures_iter(expected)
+        .filter_map(|cap| cap.name("name"))
+        .map(|m| m.as_str())
+        .map(|candidate| (candidate, candidate.to_lowercase()))
+        .filter(|(_, lower)| {
+            edit_distance(&unknown, lower) <= (lower.len() / 3).max(1)
+                || unknown.starts_with(lower.as_str())
+                || lower.starts_with(unknown.as_str())
+        })
+        .map(|(candidate, lower)| (edit_distance(&unknown, &lower), candidate))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Optimal string alignment distance, counting adjacent transpositions as a single edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn suggest_case_mismatch() {
+        assert_eq!(
+            Some(String::from("packageManager")),
+            suggest("unknown field `packagemanager`, expected one of `path`, `main`, `packageManager`")
+        );
+    }
+
+    #[test]
+    fn suggest_similar_names() {
+        assert_eq!(Some(String::from("after_rel")), suggest("unknown field `after_release`, expected `after_rel`"));
+        assert_eq!(Some(String::from("Cargo")), suggest("unknown variant `Crago`, expected one of `Cargo`, `Npm`"));
+        assert_eq!(None, suggest("unknown field `modle`, expected `vcs` or `path`"));
+    }
+
+    #[test]
+    fn point_at_unknown_key() {
+        let content = "[modules.web]\npath = \".\"\npackagemanager = \"Npm\"\n";
+        let err = toml::from_str::<toml::Table>("x = ").unwrap_err();
+        assert!(toml_error(Path::new(".panproject.toml"), "x = ", err).to_string().starts_with(".panproject.toml:1:"));
+
+        let span = narrow_span(content, 0..content.len(), "unknown field `packagemanager`, expected `path`");
+        assert_eq!((3, 1), line_col(content, span.start));
+        assert_eq!("packagemanager", &content[span]);
+    }
+
+    #[test]
+    fn report_every_unknown_key() {
+        let content = "[vcs]\ntag_templte = \"v{{version}}\"\n\n[modules.web]\npath = \".\"\npackagemanager = \"Npm\"\n";
+        let layer: serde_json::Value = toml::from_str(content).expect("Invalid toml");
+
+        let err = check_keys(Path::new("base.toml"), content, &layer).expect_err("Unknown keys accepted").to_string();
+        assert!(err.contains("base.toml:6:1: unknown field `packagemanager` in `modules.web`"), "unexpected error {err}");
+        assert!(err.contains("did you mean `packageManager`?"), "unexpected error {err}");
+        assert!(err.contains("base.toml:2:1: unknown field `tag_templte` in `vcs`"), "unexpected error {err}");
+        assert!(err.contains("did you mean `tag_template`?"), "unexpected error {err}");
+    }
+}
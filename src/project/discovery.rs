@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::project::config::{PackageManager, ProjectModule};
+use crate::project::module::PanModule;
+use crate::system::FileSystem;
+
+const SKIPPED_DIRS: [&str; 4] = [".git", ".hg", "node_modules", "target"];
+pub const ROOT_MODULE_NAME: &str = "<root>";
+
+/// Outcome of the module auto-discovery, used to explain the detected layout
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Walk the project tree and register every directory holding a versioned manifest as a module.
+pub fn discover<F: FileSystem + 'static>(root: &Path) -> anyhow::Result<(HashMap<String, ProjectModule>, DiscoveryReport)> {
+    let mut modules = HashMap::new();
+    let mut report = DiscoveryReport::default();
+    walk::<F>(root, root, &mut Vec::new(), &mut modules, &mut report)?;
+    Ok((modules, report))
+}
+
+fn walk<F: FileSystem + 'static>(
+    root: &Path,
+    dir: &Path,
+    ignores: &mut Vec<GitIgnore>,
+    modules: &mut HashMap<String, ProjectModule>,
+    report: &mut DiscoveryReport,
+) -> anyhow::Result<()> {
+    let gitignore_path = dir.join(".gitignore");
+    let has_gitignore = F::is_a_file(&gitignore_path);
+    if has_gitignore {
+        ignores.push(GitIgnore::parse(dir, &F::read_string(&gitignore_path)?));
+    }
+
+    let mut conf = ProjectModule {
+        path: dir.to_path_buf(),
+        main: dir == root,
+        package_managers: Vec::new(),
+        hooks: Default::default(),
+        builtin_hooks: Default::default(),
+        files: Default::default(),
+    };
+    for package_manager in PackageManager::detect::<F>(dir) {
+        match PanModule::<F>::extract_package(&conf, &package_manager).and_then(|p| p.extract_version()) {
+            Ok(_) => conf.package_managers.push(package_manager),
+            Err(e) => report.skipped.push((dir.to_path_buf(), format!("{} manifest without a valid version - {e}", package_manager.name()))),
+        }
+    }
+    if !conf.package_managers.is_empty() {
+        modules.insert(module_name(root, dir), conf);
+    }
+
+    let mut entries = F::read_dir(dir)?;
+    entries.sort();
+    for entry in entries {
+        let skipped = entry.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| SKIPPED_DIRS.contains(&name))
+            .unwrap_or(true);
+        // symlinked directories are not followed, they may point back to an ancestor or alias a module
+        if !skipped && F::is_a_dir(&entry) && !F::is_a_symlink(&entry) && !is_ignored(ignores, &entry) {
+            walk::<F>(root, &entry, ignores, modules, report)?;
+        }
+    }
+
+    if has_gitignore {
+        ignores.pop();
+    }
+    Ok(())
+}
+
+fn module_name(root: &Path, dir: &Path) -> String {
+    match dir.strip_prefix(root) {
+        Ok(rel) if rel.as_os_str().is_empty() => String::from(ROOT_MODULE_NAME),
+        Ok(rel) => rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => dir.to_string_lossy().to_string(),
+    }
+}
+
+fn is_ignored(ignores: &[GitIgnore], dir: &Path) -> bool {
+    ignores.iter()
+        .rev()
+        .find_map(|ignore| ignore.matches_dir(dir))
+        .unwrap_or(false)
+}
+
+struct GitIgnore {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+struct IgnoreRule {
+    matcher: GlobMatcher,
+    negated: bool,
+}
+
+impl GitIgnore {
+    fn parse(base: &Path, content: &str) -> Self {
+        let rules = content.lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, pattern) = match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, line),
+                };
+                let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
+                let glob = if pattern.contains('/') {
+                    pattern.trim_start_matches('/').to_string()
+                } else {
+                    format!("**/{pattern}")
+                };
+                let matcher = GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| log::warn!("Ignoring invalid .gitignore pattern '{line}' - {e}"))
+                    .ok()?
+                    .compile_matcher();
+                Some(IgnoreRule { matcher, negated })
+            })
+            .collect();
+
+        Self {
+            base: base.to_path_buf(),
+            rules,
+        }
+    }
+
+    /// Whether the directory is ignored (`Some(true)`), explicitly re-included (`Some(false)`) or not matched at all.
+    fn matches_dir(&self, dir: &Path) -> Option<bool> {
+        let rel = dir.strip_prefix(&self.base).ok()?;
+        self.rules.iter()
+            .rev()
+            .find(|rule| rule.matcher.is_match(rel))
+            .map(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::system::NativeSystem;
+    use crate::test_utils::TempDir;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_symlinked_dirs() {
+        let root = TempDir::new("discovery");
+        root.write("package.json", "{\"name\": \"root\", \"version\": \"0.1.0\"}");
+        root.write("crates/a/package.json", "{\"name\": \"a\", \"version\": \"0.1.0\"}");
+        std::os::unix::fs::symlink("..", root.path().join("crates/a/up")).unwrap();
+        std::os::unix::fs::symlink("a", root.path().join("crates/alias")).unwrap();
+
+        let (modules, _) = discover::<NativeSystem>(root.path()).expect("Error discovering modules");
+        let mut names = modules.into_keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(vec![ROOT_MODULE_NAME, "crates/a"], names);
+    }
+
+    #[test]
+    fn gitignore_unanchored_pattern() {
+        let ignore = GitIgnore::parse(Path::new("/repo"), "# build output\ndist/\n");
+        assert_eq!(Some(true), ignore.matches_dir(Path::new("/repo/dist")));
+        assert_eq!(Some(true), ignore.matches_dir(Path::new("/repo/packages/web/dist")));
+        assert_eq!(None, ignore.matches_dir(Path::new("/repo/packages/web")));
+    }
+
+    #[test]
+    fn gitignore_anchored_pattern() {
+        let ignore = GitIgnore::parse(Path::new("/repo"), "/vendor\nexamples/*\n!examples/kept\n");
+        assert_eq!(Some(true), ignore.matches_dir(Path::new("/repo/vendor")));
+        assert_eq!(None, ignore.matches_dir(Path::new("/repo/crates/vendor")));
+        assert_eq!(Some(true), ignore.matches_dir(Path::new("/repo/examples/demo")));
+        assert_eq!(Some(false), ignore.matches_dir(Path::new("/repo/examples/kept")));
+    }
+
+    #[test]
+    fn nested_module_name() {
+        assert_eq!(ROOT_MODULE_NAME, module_name(Path::new("/repo"), Path::new("/repo")));
+        assert_eq!("crates/core", module_name(Path::new("/repo"), Path::new("/repo/crates/core")));
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::conf::extends::normalize;
+use crate::conf::overrides::ENV_PREFIX;
+use crate::project::config::{HookCommand, HookList, HookOptions, HookTimeout};
+use crate::runner::CmdRunner;
+
+/// Points of the release where hooks are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    BeforeRel,
+    AfterRel,
+    AfterChangelog,
+    BeforeCommit,
+    AfterCommit,
+    AfterTag,
+    OnFailure,
+}
+
+impl HookStage {
+    pub const ALL: [HookStage; 7] = [
+        HookStage::BeforeRel,
+        HookStage::AfterRel,
+        HookStage::AfterChangelog,
+        HookStage::BeforeCommit,
+        HookStage::AfterCommit,
+        HookStage::AfterTag,
+        HookStage::OnFailure,
+    ];
+}
+
+impl std::fmt::Display for HookStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookStage::BeforeRel => write!(f, "before_rel"),
+            HookStage::AfterRel => write!(f, "after_rel"),
+            HookStage::AfterChangelog => write!(f, "after_changelog"),
+            HookStage::BeforeCommit => write!(f, "before_commit"),
+            HookStage::AfterCommit => write!(f, "after_commit"),
+            HookStage::AfterTag => write!(f, "after_tag"),
+            HookStage::OnFailure => write!(f, "on_failure"),
+        }
+    }
+}
+
+/// Run the commands of a hook stage in `dir`, stopping at the first failure not marked `continue_on_error`
+pub fn run_hooks(hooks: &HookList, stage: HookStage, ctx: &HookContext, dir: &Path) -> anyhow::Result<()> {
+    for (name, hook) in hooks.entries() {
+        run_hook(&name, hook, stage, ctx, dir)?;
+    }
+    Ok(())
+}
+
+/// Run a single hook command in `dir`, failures being ignored when the hook is marked `continue_on_error`
+pub fn run_hook(name: &str, hook: &HookCommand, stage: HookStage, ctx: &HookContext, dir: &Path) -> anyhow::Result<()> {
+    let options = hook.options();
+    println!("running {stage} hook {name}");
+    match run_command(&options, ctx, dir) {
+        Err(e) if options.continue_on_error => log::warn!("{stage} hook {name} failed, continuing - {e}"),
+        Err(e) => bail!("{stage} hook {name} failed - {e}"),
+        Ok(()) => {}
+    }
+    Ok(())
+}
+
+fn run_command(options: &HookOptions, ctx: &HookContext, dir: &Path) -> anyhow::Result<()> {
+    options.validate()?;
+    let (command, args) = match (&options.run, options.cmd.as_slice()) {
+        (Some(script), _) => (String::from("sh"), vec![String::from("-c"), ctx.render_shell(script)]),
+        (None, [command, args @ ..]) => (ctx.render(command), args.iter().map(|arg| ctx.render(arg)).collect()),
+        (None, []) => bail!("empty command"),
+    };
+    let cwd = match &options.cwd {
+        Some(cwd) => dir.join(ctx.render(&cwd.to_string_lossy())),
+        None => dir.to_path_buf(),
+    };
+    let env = options.env.iter()
+        .map(|(key, value)| (key.clone(), ctx.render(value)))
+        .collect::<Vec<_>>();
+
+    let mut runner = CmdRunner::build(&command, &args, cwd)?;
+    runner.envs(&ctx.env()).envs(&env);
+    if let Some(HookTimeout(timeout)) = options.timeout {
+        runner.timeout(timeout);
+    }
+    runner.run()
+}
+
+/// Absolute glob pattern of the hook outputs matched by `pattern`, relative to `dir`
+pub fn output_pattern(dir: &Path, pattern: &str) -> String {
+    let dir = globset::escape(&normalize(dir).to_string_lossy());
+    format!("{}/{}", dir.trim_end_matches('/'), pattern.trim_start_matches("./"))
+}
+
+/// Release details made available to hook commands
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub module: String,
+    pub version: semver::Version,
+    pub previous_version: semver::Version,
+    pub tag: String,
+    /// Every released module with its new version
+    pub modules: Vec<(String, semver::Version)>,
+}
+
+impl HookContext {
+    /// Template variables by name, without braces
+    pub fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("version", self.version.to_string()),
+            ("previous_version", self.previous_version.to_string()),
+            ("major", self.version.major.to_string()),
+            ("minor", self.version.minor.to_string()),
+            ("patch", self.version.patch.to_string()),
+            ("module", self.module.clone()),
+            ("tag", self.tag.clone()),
+            ("modules", self.modules.iter()
+                .map(|(name, version)| format!("{name}@{version}"))
+                .collect::<Vec<_>>()
+                .join(", ")),
+        ]
+    }
+
+    /// Replace every `{{variable}}` occurrence in the argument
+    pub fn render(&self, arg: &str) -> String {
+        self.variables()
+            .into_iter()
+            .fold(arg.to_string(), |arg, (name, value)| arg.replace(&format!("{{{{{name}}}}}"), &value))
+    }
+
+    /// Replace every `{{variable}}` occurrence in a shell script, quoting values holding shell metacharacters
+    pub fn render_shell(&self, script: &str) -> String {
+        self.variables()
+            .into_iter()
+            .fold(script.to_string(), |script, (name, value)| script.replace(&format!("{{{{{name}}}}}"), &shell_quote(&value)))
+    }
+
+    /// Variables exported to hook processes, e.g. `PANRELEASE_VERSION`
+    pub fn env(&self) -> Vec<(String, String)> {
+        self.variables()
+            .into_iter()
+            .map(|(name, value)| (format!("{ENV_PREFIX}{}", name.to_uppercase()), value))
+            .collect()
+    }
+}
+
+/// Single-quote the value for `sh` unless it is only made of characters without special meaning
+pub fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "._+-/:@=,%".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn context() -> HookContext {
+        HookContext {
+            module: String::from("core"),
+            version: semver::Version::new(1, 3, 0),
+            previous_version: semver::Version::new(1, 2, 7),
+            tag: String::from("v1.3.0"),
+            modules: vec![(String::from("core"), semver::Version::new(1, 3, 0))],
+        }
+    }
+
+    #[test]
+    fn render_template_variables() {
+        let ctx = context();
+        assert_eq!("release-core-1.3.0", ctx.render("release-{{module}}-{{version}}"));
+        assert_eq!("1.2.7..v1.3.0 (1.3.0)", ctx.render("{{previous_version}}..{{tag}} ({{major}}.{{minor}}.{{patch}})"));
+        assert_eq!("{{unknown}}", ctx.render("{{unknown}}"));
+    }
+
+    #[test]
+    fn quote_shell_values() {
+        assert_eq!("1.3.0+build.5", shell_quote("1.3.0+build.5"));
+        assert_eq!("'my module'", shell_quote("my module"));
+        assert_eq!("'it'\\''s'", shell_quote("it's"));
+        let ctx = HookContext { module: String::from("web app"), ..context() };
+        assert_eq!("echo 'web app' v1.3.0", ctx.render_shell("echo {{module}} {{tag}}"));
+    }
+
+    #[test]
+    fn export_prefixed_env() {
+        let env = context().env();
+        assert!(env.contains(&(String::from("PANRELEASE_PREVIOUS_VERSION"), String::from("1.2.7"))));
+        assert!(env.contains(&(String::from("PANRELEASE_TAG"), String::from("v1.3.0"))));
+    }
+}
@@ -1,3 +1,7 @@
 pub mod core;
 pub mod config;
-pub mod module;
\ No newline at end of file
+pub mod diagnostics;
+pub mod discovery;
+pub mod hooks;
+pub mod module;
+pub mod version_files;
\ No newline at end of file
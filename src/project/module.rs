@@ -1,21 +1,26 @@
 use std::marker::PhantomData;
-use std::path::PathBuf;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 
+use crate::conf::extends::normalize;
+use crate::package::android::AndroidPackage;
 use crate::package::cargo::CargoPackage;
+use crate::package::custom::CustomPackage;
 use crate::package::gradle::GradlePackage;
 use crate::package::maven::MavenPackage;
 use crate::package::npm::NpmPackage;
 use crate::package::PanPackage;
-use crate::project::config::{PackageManager, ProjectModule};
-use crate::runner::CmdRunner;
+use crate::project::config::{BuiltinHooks, PackageManager, ProjectModule};
+use crate::project::hooks;
+use crate::project::hooks::{HookContext, HookStage};
+use crate::project::version_files::VersionFiles;
 use crate::system::FileSystem;
 
 pub struct PanModule<F> {
     name: String,
     conf: ProjectModule,
-    package: Box<dyn PanPackage>,
+    packages: Vec<Box<dyn PanPackage>>,
+    files: VersionFiles<F>,
     filesystem: PhantomData<F>,
 }
 
@@ -23,62 +28,92 @@ impl<F: FileSystem + 'static> PanModule<F> {
     pub fn new(name: String, conf: ProjectModule) -> anyhow::Result<Self> {
         Ok(Self {
             name,
-            package: Self::extract_package(&conf)?,
+            packages: conf.package_managers
+                .iter()
+                .map(|package_manager| Self::extract_package(&conf, package_manager))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            files: VersionFiles::new(conf.path.clone(), conf.files.clone()),
             conf,
             filesystem: PhantomData,
         })
     }
 
-    pub fn detect(path: PathBuf) -> anyhow::Result<Option<Self>> {
-        let Some(package_manager) = PackageManager::detect::<F>(&path) else {
-            return Ok(None);
-        };
-        let conf = ProjectModule {
-            path,
-            main: false,
-            package_manager,
-            hooks: Default::default(),
-        };
-
-        Ok(Some(Self {
-            name: String::from("<detected>"),
-            package: Self::extract_package(&conf)?,
-            conf,
-            filesystem: PhantomData,
-        }))
-    }
-
-    fn extract_package(conf: &ProjectModule) -> anyhow::Result<Box<dyn PanPackage>> {
-        Ok(match conf.package_manager {
+    pub fn extract_package(conf: &ProjectModule, package_manager: &PackageManager) -> anyhow::Result<Box<dyn PanPackage>> {
+        Ok(match package_manager {
             PackageManager::Cargo => Box::new(CargoPackage::<F>::new(conf.path.clone())?),
             PackageManager::Npm => Box::new(NpmPackage::<F>::new(conf.path.clone())?),
             PackageManager::Maven => Box::new(MavenPackage::<F>::new(conf.path.clone())?),
             PackageManager::Gradle => Box::new(GradlePackage::<F>::new(conf.path.clone())?),
+            PackageManager::Android(android_conf) => Box::new(AndroidPackage::<F>::new(conf.path.clone(), android_conf)?),
+            PackageManager::Custom(custom_conf) => Box::new(CustomPackage::<F>::new(conf.path.clone(), custom_conf)?),
         })
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn conf(&self) -> &ProjectModule {
+        &self.conf
+    }
+
     pub fn extract_version(&self) -> anyhow::Result<semver::Version> {
-        self.package.extract_version()
+        let Some(package) = self.packages.first() else {
+            bail!("No package manager defined for module {}", self.name);
+        };
+        package.extract_version()
     }
 
     pub fn set_version(&mut self, version: &semver::Version) -> anyhow::Result<()> {
-        self.package.set_version(version)
+        let current = self.extract_version()?;
+        self.files.set_version(&current, version)
+            .with_context(|| format!("Error updating version files of module {}", self.name))?;
+        for package in self.packages.iter_mut() {
+            package.set_version(version)?;
+        }
+        Ok(())
     }
 
     pub fn persist(&self) -> anyhow::Result<()> {
-        self.package.persist()
+        for package in self.packages.iter() {
+            package.persist()?;
+        }
+        self.files.persist()
     }
 
-    pub fn hook_after_rel(&mut self) -> anyhow::Result<()> {
-        self.package.hook_after_rel()?;
-        for (name, full_command) in self.conf.hooks.after_rel.iter() {
-            let [command, args @ ..] = full_command.as_slice() else {
-                bail!("error reading '{name}' after_rel hook");
-            };
-            println!("running after_rel hook {name}");
-            let mut runner = CmdRunner::build(command, args, &self.conf.path)?;
-            runner.run()?;
+    pub fn hook_after_rel(&mut self, ctx: &HookContext) -> anyhow::Result<()> {
+        match &self.conf.builtin_hooks {
+            BuiltinHooks::Enabled(true) => {
+                for package in self.packages.iter() {
+                    package.hook_after_rel()?;
+                }
+            }
+            BuiltinHooks::Enabled(false) => {}
+            BuiltinHooks::Override(hook) => {
+                hooks::run_hook("builtin_hooks", hook, HookStage::AfterRel, ctx, &self.conf.path)?;
+            }
         }
-        Ok(())
+        self.run_hooks(HookStage::AfterRel, ctx)
+    }
+
+    /// Absolute glob patterns of the files changed by the hooks of the module
+    pub fn hook_outputs(&self) -> Vec<String> {
+        let mut patterns = self.conf.hooks.outputs();
+        let mut files = Vec::new();
+        match &self.conf.builtin_hooks {
+            BuiltinHooks::Enabled(true) => files.extend(self.packages.iter().flat_map(|package| package.hook_outputs())),
+            BuiltinHooks::Enabled(false) => {}
+            BuiltinHooks::Override(hook) => patterns.extend(hook.options().stage),
+        }
+        patterns.iter()
+            .map(|pattern| hooks::output_pattern(&self.conf.path, pattern))
+            .chain(files.iter().map(|file| globset::escape(&normalize(file).to_string_lossy())))
+            .collect()
+    }
+
+    /// Run the module hooks configured for the stage in the module directory
+    pub fn run_hooks(&self, stage: HookStage, ctx: &HookContext) -> anyhow::Result<()> {
+        hooks::run_hooks(self.conf.hooks.stage(stage), stage, ctx, &self.conf.path)
+            .with_context(|| format!("Error running hooks of module {}", self.name))
     }
 }
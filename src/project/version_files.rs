@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use regex::Regex;
+use semver::Version;
+
+use crate::project::config::VersionFile;
+use crate::system::FileSystem;
+
+pub struct VersionFiles<F> {
+    base_path: PathBuf,
+    entries: Vec<VersionFile>,
+    pending: BTreeMap<PathBuf, String>,
+    filesystem: PhantomData<F>,
+}
+
+impl<F: FileSystem> VersionFiles<F> {
+    pub fn new(base_path: PathBuf, entries: Vec<VersionFile>) -> Self {
+        Self {
+            base_path,
+            entries,
+            pending: Default::default(),
+            filesystem: PhantomData,
+        }
+    }
+
+    pub fn set_version(&mut self, current: &Version, new: &Version) -> anyhow::Result<()> {
+        for entry in self.entries.iter() {
+            let file_path = self.base_path.join(&entry.path);
+            let content = match self.pending.remove(&file_path) {
+                Some(content) => content,
+                None if entry.optional && !F::is_a_file(&file_path) => continue,
+                None => F::read_string(&file_path)
+                    .with_context(|| format!("Error reading version file {file_path:?}"))?,
+            };
+            let updated = match apply_entry(&content, entry, current, new)? {
+                Some(updated) => updated,
+                None if entry.optional => content,
+                None => anyhow::bail!("Could not find '{}' in {file_path:?}", entry.search),
+            };
+            self.pending.insert(file_path, updated);
+        }
+        Ok(())
+    }
+
+    pub fn persist(&self) -> anyhow::Result<()> {
+        for (path, content) in self.pending.iter() {
+            F::write_string(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+fn apply_entry(content: &str, entry: &VersionFile, current: &Version, new: &Version) -> anyhow::Result<Option<String>> {
+    let search = entry.search
+        .replace("{{current_version}}", &regex::escape(&current.to_string()))
+        .replace("{{new_version}}", &regex::escape(&new.to_string()));
+    let replace = entry.replace
+        .replace("{{current_version}}", &current.to_string())
+        .replace("{{new_version}}", &new.to_string());
+
+    let search_regex = Regex::new(&search)
+        .map_err(|e| anyhow!("Invalid search pattern '{}' - {e}", entry.search))?;
+    if !search_regex.is_match(content) {
+        return Ok(None);
+    }
+    Ok(Some(search_regex.replace_all(content, replace.as_str()).to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(search: &str, replace: &str) -> VersionFile {
+        VersionFile {
+            path: PathBuf::from("README.md"),
+            search: String::from(search),
+            replace: String::from(replace),
+            optional: false,
+        }
+    }
+
+    #[test]
+    fn replace_every_occurrence() {
+        let updated = apply_entry(
+            "panrelease = \"1.2.3\"\nnpx panrelease@1.2.3",
+            &entry("{{current_version}}", "{{new_version}}"),
+            &Version::new(1, 2, 3),
+            &Version::new(1, 3, 0),
+        ).unwrap();
+        assert_eq!(Some(String::from("panrelease = \"1.3.0\"\nnpx panrelease@1.3.0")), updated);
+    }
+
+    #[test]
+    fn replace_with_capture_groups() {
+        let updated = apply_entry(
+            "pub const VERSION: &str = \"1.2.3\";",
+            &entry(r#"(?P<decl>VERSION: &str = )"[^"]*""#, r#"${decl}"{{new_version}}""#),
+            &Version::new(1, 2, 3),
+            &Version::new(2, 0, 0),
+        ).unwrap();
+        assert_eq!(Some(String::from("pub const VERSION: &str = \"2.0.0\";")), updated);
+    }
+
+    #[test]
+    fn escape_current_version() {
+        let updated = apply_entry(
+            "1a2b3",
+            &entry("{{current_version}}", "{{new_version}}"),
+            &Version::new(1, 2, 3),
+            &Version::new(1, 2, 4),
+        ).unwrap();
+        assert_eq!(None, updated);
+    }
+}
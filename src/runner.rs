@@ -1,14 +1,16 @@
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus, Output, Stdio};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use serde_json::json;
 use wasm_bindgen::JsValue;
-use crate::wasm_utils::exec;
+use crate::wasm_utils::exec_file;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub struct CmdRunner {
     command: Command,
+    timeout: Option<Duration>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -21,12 +23,42 @@ impl CmdRunner {
 
         Ok(Self {
             command,
+            timeout: None,
         })
     }
 
+    /// Kill the process when it runs longer than `timeout`
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Add variables to the environment of the process
+    pub fn envs(&mut self, vars: &[(String, String)]) -> &mut Self {
+        self.command.envs(vars.iter().map(|(key, value)| (key, value)));
+        self
+    }
+
     pub fn run(&mut self) -> Result<()> {
         let mut process = self.command.spawn()?;
-        let exit_status = process.wait()?;
+        let exit_status = match self.timeout {
+            None => process.wait()?,
+            Some(timeout) => {
+                let deadline = Instant::now().checked_add(timeout)
+                    .ok_or_else(|| anyhow!("timeout {timeout:?} is too large"))?;
+                loop {
+                    if let Some(exit_status) = process.try_wait()? {
+                        break exit_status;
+                    }
+                    if Instant::now() >= deadline {
+                        Self::kill(&mut process)?;
+                        process.wait()?;
+                        anyhow::bail!("process killed after exceeding the {timeout:?} timeout");
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+            }
+        };
         if exit_status.success() {
             Ok(())
         } else {
@@ -34,6 +66,63 @@ impl CmdRunner {
         }
     }
 
+    /// Kill the process and the processes it started, e.g. the commands of a `sh -c` script.
+    /// The process stays in the process group of panrelease, so Ctrl-C and job control reach it as usual.
+    #[cfg(unix)]
+    fn kill(process: &mut Child) -> Result<()> {
+        let descendants = Self::descendants(process.id());
+        process.kill()?;
+        for pid in descendants {
+            // SAFETY: kill has no memory safety requirements, a process that already exited is reported as an error
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn kill(process: &mut Child) -> Result<()> {
+        Ok(process.kill()?)
+    }
+
+    /// Processes started by `pid` directly or not, parents first, as listed by ps
+    #[cfg(unix)]
+    fn descendants(pid: u32) -> Vec<u32> {
+        let Ok(out) = Command::new("ps").args(["-A", "-o", "pid=", "-o", "ppid="]).output() else {
+            return Vec::new();
+        };
+        let processes = String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut ids = line.split_whitespace().map(|id| id.parse::<u32>().ok());
+                Some((ids.next()??, ids.next()??))
+            })
+            .collect::<Vec<_>>();
+        let mut descendants = vec![pid];
+        let mut idx = 0;
+        while let Some(parent) = descendants.get(idx).copied() {
+            descendants.extend(processes.iter().filter(|(_, ppid)| *ppid == parent).map(|(pid, _)| *pid));
+            idx += 1;
+        }
+        descendants.split_off(1)
+    }
+
+    /// Feed `input` to the process and collect its output
+    #[cfg(feature = "git2")]
+    pub fn output_with_input(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut process = self.command
+            .stdout(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = process.stdin.take() {
+            std::io::Write::write_all(&mut stdin, input)?;
+        }
+        let out = process.wait_with_output()?;
+        if out.status.success() {
+            Ok(out.stdout)
+        } else {
+            anyhow::bail!("process exited with {}", out.status)
+        }
+    }
+
     pub fn output(&mut self) -> Result<Vec<u8>> {
         let out = self.command.output()?;
         let exit_status = out.status;
@@ -48,33 +137,119 @@ impl CmdRunner {
 #[cfg(target_arch = "wasm32")]
 pub struct CmdRunner {
     command: String,
-    dir: PathBuf
+    args: Vec<String>,
+    dir: PathBuf,
+    env: Vec<(String, String)>,
+    timeout: Option<Duration>,
 }
 
 #[cfg(target_arch = "wasm32")]
 impl CmdRunner {
     pub fn build(cmd_name: &str, args: &[String], dir: impl AsRef<Path>) -> Result<Self> {
         Ok(Self {
-            command: format!("{cmd_name} {}", args.join(" ")),
+            command: cmd_name.to_string(),
+            args: args.to_vec(),
             dir: dir.as_ref().to_path_buf(),
+            env: Vec::new(),
+            timeout: None,
         })
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        let opts = serde_wasm_bindgen::to_value(&json!({ "cwd": &self.dir }))
-            .expect("Error serializing options");
-        let out = exec(self.command.clone(), opts)
+    /// Kill the process when it runs longer than `timeout`
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Add variables to the environment of the process
+    pub fn envs(&mut self, vars: &[(String, String)]) -> &mut Self {
+        self.env.extend_from_slice(vars);
+        self
+    }
+
+    /// Options of execFileSync, the env option replaces the whole environment so the current one is extended.
+    /// Serialized as a plain object, execFileSync ignores the `Map` serde_wasm_bindgen produces by default.
+    fn exec_opts(&self) -> Result<JsValue> {
+        let mut env: std::collections::HashMap<String, String> = serde_wasm_bindgen::from_value(crate::wasm_utils::process::ENV.clone())
+            .map_err(|e| anyhow!("Error reading environment - {e:?}"))?;
+        env.extend(self.env.iter().cloned());
+        let mut opts = json!({ "cwd": &self.dir, "env": env });
+        if let Some(timeout) = self.timeout {
+            opts["timeout"] = json!(timeout.as_millis() as u64);
+        }
+        serde::Serialize::serialize(&opts, &serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| anyhow!("Error serializing options - {e:?}"))
+    }
+
+    /// Run the command without a shell, so that arguments are passed as they are
+    fn exec(&self, opts: JsValue) -> Result<Vec<u8>> {
+        let args = self.args.iter().map(JsValue::from).collect::<js_sys::Array>();
+        exec_file(self.command.clone(), args, opts)
             .map_err(|e| anyhow!("Error executing command - {e:?}"))
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        let opts = self.exec_opts()?;
+        let out = self.exec(opts)
             .and_then(|res| Ok(String::from_utf8(res)?))?;
         crate::wasm_utils::log(&out);
         Ok(())
     }
 
     pub fn output(&mut self) -> Result<Vec<u8>> {
-        let opts = serde_wasm_bindgen::to_value(&json!({ "cwd": &self.dir }))
-            .expect("Error serializing options");
-        let out = exec(self.command.clone(), opts)
-            .map_err(|e| anyhow!("Error executing command - {e:?}"))?;
+        let opts = self.exec_opts()?;
+        let out = self.exec(opts)?;
         Ok(out)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn pass_env_and_cwd_to_command() {
+        let dir = crate::wasm_utils::process::cwd();
+        let env = [(String::from("PANRELEASE_VERSION"), String::from("1.3.0"))];
+
+        let out = CmdRunner::build("printenv", &[String::from("PANRELEASE_VERSION")], &dir)
+            .expect("Error building command")
+            .envs(&env)
+            .output()
+            .expect("Error running command");
+        assert_eq!("1.3.0", String::from_utf8_lossy(&out).trim());
+
+        let out = CmdRunner::build("pwd", &[], &dir)
+            .expect("Error building command")
+            .output()
+            .expect("Error running command");
+        assert_eq!(dir, String::from_utf8_lossy(&out).trim());
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use crate::test_utils::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn kill_script_commands_on_timeout() {
+        let dir = TempDir::new("timeout");
+        let script = String::from("(sleep 1 && touch finished) & wait");
+
+        let started = Instant::now();
+        let err = CmdRunner::build("sh", &[String::from("-c"), script], dir.path())
+            .expect("Error building command")
+            .timeout(Duration::from_millis(100))
+            .run()
+            .expect_err("Command not killed");
+        assert!(err.to_string().contains("timeout"), "unexpected error {err}");
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(!dir.path().join("finished").exists(), "script command kept running after the timeout");
+    }
+}
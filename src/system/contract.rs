@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use anyhow::Result;
 
@@ -12,5 +12,9 @@ pub trait FileSystem {
     fn current_dir() -> Result<PathBuf>;
     fn is_a_dir(path: &Path) -> bool;
     fn is_a_file(path: &Path) -> bool;
+    fn is_a_symlink(path: &Path) -> bool;
+    fn read_dir(path: &Path) -> Result<Vec<PathBuf>>;
+    /// Paths written through `write_string` by the current thread since the previous call
+    fn take_written() -> BTreeSet<PathBuf>;
 }
 
@@ -4,4 +4,4 @@ mod nodejs_system;
 
 pub use contract::{EnvVars, FileSystem};
 pub use native_system::NativeSystem;
-pub use nodejs_system::NodeJsSystem;
\ No newline at end of file
+pub use nodejs_system::NodeJsSystem;
@@ -1,10 +1,29 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
-use crate::system::contract::FileSystem;
+use crate::system::contract::{EnvVars, FileSystem};
+
+thread_local! {
+    static WRITTEN: RefCell<BTreeSet<PathBuf>> = const { RefCell::new(BTreeSet::new()) };
+}
 
 #[derive(Default)]
 pub struct NativeSystem;
 
+impl EnvVars for NativeSystem {
+    fn prefixed(prefix: &str) -> anyhow::Result<HashMap<String, String>> {
+        let filtered_env = std::env::vars()
+            .filter_map(|(key, value)|
+                key
+                    .strip_prefix(prefix)
+                    .map(|subkey| (subkey.to_string(), value))
+            )
+            .collect();
+        Ok(filtered_env)
+    }
+}
+
 impl FileSystem for NativeSystem {
     fn read_string(path: &Path) -> anyhow::Result<String> {
         let content = std::fs::read_to_string(path)?;
@@ -13,6 +32,7 @@ impl FileSystem for NativeSystem {
 
     fn write_string(path: &Path, content: &str) -> anyhow::Result<()> {
         std::fs::write(path, content)?;
+        WRITTEN.with(|written| written.borrow_mut().insert(path.to_path_buf()));
         Ok(())
     }
 
@@ -27,4 +47,19 @@ impl FileSystem for NativeSystem {
     fn is_a_file(path: &Path) -> bool {
         path.is_file()
     }
+
+    fn is_a_symlink(path: &Path) -> bool {
+        path.symlink_metadata().is_ok_and(|meta| meta.file_type().is_symlink())
+    }
+
+    fn read_dir(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let entries = std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    fn take_written() -> BTreeSet<PathBuf> {
+        WRITTEN.with(|written| std::mem::take(&mut *written.borrow_mut()))
+    }
 }
\ No newline at end of file
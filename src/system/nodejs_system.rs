@@ -1,9 +1,15 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use anyhow::anyhow;
+use wasm_bindgen::{JsCast, JsValue};
 use crate::system::contract::{EnvVars, FileSystem};
 use crate::wasm_utils;
 
+thread_local! {
+    static WRITTEN: RefCell<BTreeSet<PathBuf>> = const { RefCell::new(BTreeSet::new()) };
+}
+
 #[derive(Default)]
 pub struct NodeJsSystem;
 
@@ -31,6 +37,7 @@ impl FileSystem for NodeJsSystem {
     fn write_string(path: &Path, content: &str) -> anyhow::Result<()> {
         wasm_utils::write_file(path.to_str().expect("invalid path"), content)
             .map_err(|e| anyhow!("Error writing file - {e:?}"))?;
+        WRITTEN.with(|written| written.borrow_mut().insert(path.to_path_buf()));
         Ok(())
     }
 
@@ -40,12 +47,44 @@ impl FileSystem for NodeJsSystem {
     }
 
     fn is_a_dir(path: &Path) -> bool {
-        wasm_utils::exists(path.to_str().expect("Invalid path"))
-            .expect("Error checking file existence")
+        stat_check(path, wasm_utils::stat, "isDirectory")
     }
 
     fn is_a_file(path: &Path) -> bool {
-        wasm_utils::exists(path.to_str().expect("Invalid path"))
-            .expect("Error checking file existence")
+        stat_check(path, wasm_utils::stat, "isFile")
+    }
+
+    fn is_a_symlink(path: &Path) -> bool {
+        stat_check(path, wasm_utils::lstat, "isSymbolicLink")
+    }
+
+    fn read_dir(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let entries = wasm_utils::read_dir(path.to_str().expect("invalid path"))
+            .map_err(|e| anyhow!("Error reading directory - {e:?}"))?;
+        Ok(entries.iter()
+            .filter_map(|entry| entry.as_string())
+            .map(|entry| path.join(entry))
+            .collect())
+    }
+
+    fn take_written() -> BTreeSet<PathBuf> {
+        WRITTEN.with(|written| std::mem::take(&mut *written.borrow_mut()))
+    }
+}
+
+fn stat_check(path: &Path, stat: fn(&str) -> Result<JsValue, JsValue>, check: &str) -> bool {
+    let path_str = path.to_str().expect("Invalid path");
+    let exists = wasm_utils::exists(path_str)
+        .expect("Error checking file existence");
+    if !exists {
+        return false;
     }
+    let stats = stat(path_str)
+        .expect("Error reading file stats");
+    js_sys::Reflect::get(&stats, &JsValue::from_str(check))
+        .ok()
+        .and_then(|f| f.dyn_into::<js_sys::Function>().ok())
+        .and_then(|f| f.call0(&stats).ok())
+        .and_then(|res| res.as_bool())
+        .unwrap_or(false)
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Empty directory in the system temp dir, removed on drop so that failed tests clean up too
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "panrelease-{name}-{}-{}",
+            std::process::id(),
+            DIR_COUNTER.fetch_add(1, Ordering::SeqCst),
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("Error creating temp dir");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write a file relative to the directory, creating its parents
+    pub fn write(&self, file: &str, content: &str) {
+        let file = self.path.join(file);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).expect("Error creating parent dir");
+        }
+        std::fs::write(file, content).expect("Error writing file");
+    }
+
+    pub fn read(&self, file: &str) -> String {
+        std::fs::read_to_string(self.path.join(file)).expect("Error reading file")
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Git repository in a [`TempDir`], with a local identity and signing disabled
+pub struct TempRepo {
+    dir: TempDir,
+}
+
+impl TempRepo {
+    pub fn new(name: &str) -> Self {
+        let repo = Self { dir: TempDir::new(name) };
+        repo.git(&["init", "-q"]);
+        repo.git(&["config", "user.name", "panrelease"]);
+        repo.git(&["config", "user.email", "panrelease@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo.git(&["config", "tag.gpgsign", "false"]);
+        repo
+    }
+
+    /// Bare repository, e.g. to push to
+    pub fn bare(name: &str) -> Self {
+        let repo = Self { dir: TempDir::new(name) };
+        repo.git(&["init", "-q", "--bare"]);
+        repo
+    }
+
+    /// Run git in the repository, returning its output
+    pub fn git(&self, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(self.dir.path())
+            .output()
+            .expect("Error running git");
+        assert!(out.status.success(), "git {args:?} failed: {}", String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    }
+
+    pub fn commit_all(&self, message: &str) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+}
+
+impl Deref for TempRepo {
+    type Target = TempDir;
+
+    fn deref(&self) -> &TempDir {
+        &self.dir
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use anyhow::Context;
+use globset::{GlobBuilder, GlobSetBuilder};
+
+use crate::git;
+use crate::hg;
+use crate::project::config::VcsConfig;
+use crate::system::FileSystem;
+
+mod none;
+
+/// Uncommitted change of a tracked file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Path relative to the repository root, `/` separated
+    pub path: String,
+    /// Status code reported by the version control, e.g. ` M` for a file modified but not staged in git
+    pub status: String,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.status, self.path)
+    }
+}
+
+/// Changes not matching any of the `allow_dirty` patterns.
+/// As in .gitignore, patterns without `/` match files in any directory
+pub fn disallowed_changes(changes: Vec<Change>, allow_dirty: &[String]) -> anyhow::Result<Vec<Change>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in allow_dirty {
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+        builder.add(GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid allow_dirty pattern '{pattern}'"))?);
+    }
+    let allowed = builder.build()?;
+    Ok(changes.into_iter()
+        .filter(|change| !allowed.is_match(&change.path))
+        .collect())
+}
+
+/// Version control operations used to release a project
+// history and remote operations are not part of the release flow yet
+#[allow(dead_code)]
+pub trait VcsBackend {
+    /// Uncommitted changes of tracked files, untracked files are never reported as they are left out of release commits
+    fn status(&self) -> anyhow::Result<Vec<Change>>;
+    /// Untracked files not ignored, relative to the repository root
+    fn untracked(&self) -> anyhow::Result<Vec<String>>;
+    /// Commit exactly the given paths, relative to the repository root, leaving any other change out.
+    /// An empty list is an error, see [`check_commit_paths`]
+    fn commit(&self, message: &str, paths: &[String]) -> anyhow::Result<()>;
+    /// Tag the current commit, creating an annotated tag when a message is given
+    fn tag(&self, name: &str, message: Option<&str>) -> anyhow::Result<()>;
+    /// Subjects of the latest `limit` commits, newest first
+    fn log(&self, limit: usize) -> anyhow::Result<Vec<String>>;
+    /// Names of every tag in the repository
+    fn tags(&self) -> anyhow::Result<Vec<String>>;
+    /// Name of the current branch, `None` when not on a branch
+    fn branch(&self) -> anyhow::Result<Option<String>>;
+    /// Push the current branch and the given tags to the default remote
+    fn push(&self, tags: &[String]) -> anyhow::Result<()>;
+}
+
+/// Fail on an empty list of paths to commit, that the vcs tools would take as every pending change
+pub fn check_commit_paths(paths: &[String]) -> anyhow::Result<()> {
+    if paths.is_empty() {
+        anyhow::bail!("No file changed by the release, nothing to commit");
+    }
+    Ok(())
+}
+
+/// Nearest directory from `path` upwards holding a git working tree or a mercurial repository
+pub fn find_root<F: FileSystem>(path: &Path) -> anyhow::Result<&Path> {
+    for dir in path.ancestors() {
+        if git::git_dir::<F>(dir)?.is_some() || F::is_a_dir(&dir.join(".hg")) {
+            return Ok(dir);
+        }
+    }
+    anyhow::bail!("Could not find repo dir")
+}
+
+/// Open the backend selected by the configuration on the repository containing `path`
+pub fn open<F: FileSystem>(conf: &VcsConfig, path: &Path) -> anyhow::Result<Box<dyn VcsBackend>> {
+    match conf {
+        VcsConfig::Git(git_conf) => git::open::<F>(git_conf.clone(), path),
+        VcsConfig::Mercurial(hg_conf) => Ok(Box::new(hg::HgRepo::open::<F>(hg_conf.clone(), path)?)),
+        VcsConfig::None => Ok(Box::new(none::NoVcs)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(path: &str) -> Change {
+        Change { path: path.to_string(), status: String::from(" M") }
+    }
+
+    #[test]
+    fn filter_allowed_changes() {
+        let changes = vec![change("docs/guide/intro.md"), change("web/api.generated.ts"), change("src/lib.rs"), change("pkg/docs/readme.md")];
+        let disallowed = disallowed_changes(changes, &[String::from("docs/**"), String::from("*.generated.ts")])
+            .expect("Invalid patterns");
+        assert_eq!(vec![change("src/lib.rs"), change("pkg/docs/readme.md")], disallowed);
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::bail;
+
+use crate::vcs::{Change, VcsBackend};
+
+/// Backend of projects released without version control, every change is left in the working directory
+pub struct NoVcs;
+
+impl VcsBackend for NoVcs {
+    fn status(&self) -> anyhow::Result<Vec<Change>> {
+        Ok(Vec::new())
+    }
+
+    fn untracked(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn commit(&self, _message: &str, _paths: &[String]) -> anyhow::Result<()> {
+        log::info!("No version control configured, skipping commit");
+        Ok(())
+    }
+
+    fn tag(&self, name: &str, _message: Option<&str>) -> anyhow::Result<()> {
+        log::info!("No version control configured, skipping tag {name}");
+        Ok(())
+    }
+
+    fn log(&self, _limit: usize) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn tags(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn branch(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn push(&self, _tags: &[String]) -> anyhow::Result<()> {
+        bail!("Cannot push, no version control configured")
+    }
+}
@@ -42,12 +42,21 @@ extern "C" {
 
     #[wasm_bindgen(js_name = existsSync, catch)]
     pub fn exists(path: &str) -> Result<bool, JsValue>;
+
+    #[wasm_bindgen(js_name = statSync, catch)]
+    pub fn stat(path: &str) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_name = readdirSync, catch)]
+    pub fn read_dir(path: &str) -> Result<js_sys::Array, JsValue>;
 }
 
 #[wasm_bindgen(module = "child_process")]
 extern "C" {
     #[wasm_bindgen(js_name = execSync, catch)]
     pub fn exec(command: String, opts: JsValue) -> Result<Vec<u8>, JsValue>;
+
+    #[wasm_bindgen(js_name = execFileSync, catch)]
+    pub fn exec_file(file: String, args: js_sys::Array, opts: JsValue) -> Result<Vec<u8>, JsValue>;
 }
 
 pub mod process {